@@ -0,0 +1,285 @@
+// amount.rs
+//
+// Amounts of nock, kept as a whole number of nicks (1 NOCK = 65536 nicks). All
+// arithmetic is checked. Amounts go over serde as a decimal string of nicks, so
+// large values survive JavaScript numbers, and plain numbers are accepted too.
+// Note values from the kernel come as Hoon @ud, with dots between thousands, and
+// are read with `from_ud` rather than by dropping the dots.
+
+use std::fmt;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+pub const NICKS_PER_NOCK: u64 = 1 << 16;
+
+// digits needed to write a fraction of a nock exactly, 10^16 / 2^16 = 5^16
+const FRACTION_DIGITS: usize = 16;
+const FRACTION_SCALE: u64 = 152_587_890_625;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+
+    pub const fn from_nicks(nicks: u64) -> Self {
+        Self(nicks)
+    }
+
+    pub const fn nicks(self) -> u64 {
+        self.0
+    }
+
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+
+    pub fn checked_mul(self, factor: u64) -> Option<Amount> {
+        self.0.checked_mul(factor).map(Amount)
+    }
+
+    /// Adds up `amounts`, `None` if the total overflows.
+    pub fn checked_sum<I: IntoIterator<Item = Amount>>(amounts: I) -> Option<Amount> {
+        amounts
+            .into_iter()
+            .try_fold(Amount::ZERO, |sum, amount| sum.checked_add(amount))
+    }
+
+    /// Parses a Hoon @ud such as `1.234.567`.
+    pub fn from_ud(ud: &str) -> Result<Amount, String> {
+        let invalid = || format!("invalid @ud amount: {:?}", ud);
+        let mut groups = ud.split('.');
+        let first = groups.next().unwrap_or_default();
+        if first.is_empty()
+            || first.len() > 3
+            || (first.len() > 1 && first.starts_with('0'))
+            || !first.bytes().all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        let mut nicks = first.parse::<u64>().map_err(|_| invalid())?;
+        for group in groups {
+            if group.len() != 3 || !group.bytes().all(|b| b.is_ascii_digit()) || nicks == 0 {
+                return Err(invalid());
+            }
+            nicks = nicks
+                .checked_mul(1000)
+                .and_then(|n| n.checked_add(group.parse::<u64>().ok()?))
+                .ok_or_else(|| format!("amount too large: {}", ud))?;
+        }
+        Ok(Amount(nicks))
+    }
+
+    /// Writes the amount as a Hoon @ud.
+    pub fn to_ud(self) -> String {
+        let digits = self.0.to_string();
+        let mut ud = String::with_capacity(digits.len() + digits.len() / 3);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                ud.push('.');
+            }
+            ud.push(digit);
+        }
+        ud
+    }
+}
+
+/// Writes the amount in NOCK with as many decimals as it needs, e.g. `1.5` for 98304 nicks.
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let whole = self.0 / NICKS_PER_NOCK;
+        let fraction = self.0 % NICKS_PER_NOCK;
+        if fraction == 0 {
+            return write!(f, "{}", whole);
+        }
+        let decimals = format!(
+            "{:0width$}",
+            fraction * FRACTION_SCALE,
+            width = FRACTION_DIGITS
+        );
+        write!(f, "{}.{}", whole, decimals.trim_end_matches('0'))
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AmountVisitor;
+
+        impl Visitor<'_> for AmountVisitor {
+            type Value = Amount;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an amount in nicks")
+            }
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Amount, E> {
+                Ok(Amount(value))
+            }
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Amount, E> {
+                u64::try_from(value)
+                    .map(Amount)
+                    .map_err(|_| E::custom("amount can't be negative"))
+            }
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Amount, E> {
+                if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(E::custom(format!("invalid amount: {:?}", value)));
+                }
+                value
+                    .parse::<u64>()
+                    .map(Amount)
+                    .map_err(|_| E::custom(format!("amount too large: {}", value)))
+            }
+        }
+
+        deserializer.deserialize_any(AmountVisitor)
+    }
+}
+
+/// Serde format of amounts written as a Hoon @ud, for `#[serde(with = "ud")]`.
+pub mod ud {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::Amount;
+
+    pub fn serialize<S: Serializer>(amount: &Amount, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&amount.to_ud())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+        let ud = String::deserialize(deserializer)?;
+        Amount::from_ud(&ud).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ud_round_trips() {
+        for (nicks, ud) in [
+            (0, "0"),
+            (7, "7"),
+            (999, "999"),
+            (1_000, "1.000"),
+            (65_536, "65.536"),
+            (1_234_567, "1.234.567"),
+            (u64::MAX, "18.446.744.073.709.551.615"),
+        ] {
+            assert_eq!(Amount::from_nicks(nicks).to_ud(), ud);
+            assert_eq!(Amount::from_ud(ud), Ok(Amount::from_nicks(nicks)));
+        }
+    }
+
+    #[test]
+    fn malformed_ud_is_rejected() {
+        for ud in [
+            "", ".", "1.", ".123", "1..234", "1.23", "1.2345", "1234", "01", "00", "0.123",
+            "01.234", "-1", "+1", "1,234", " 1", "1.23a", "1_000",
+        ] {
+            assert!(Amount::from_ud(ud).is_err(), "{:?} is accepted", ud);
+        }
+    }
+
+    #[test]
+    fn ud_overflow_is_rejected() {
+        let err = Amount::from_ud("18.446.744.073.709.551.616").unwrap_err();
+        assert!(err.contains("too large"), "{}", err);
+        assert!(Amount::from_ud("1.000.000.000.000.000.000.000").is_err());
+    }
+
+    #[test]
+    fn displays_nock_with_the_decimals_it_needs() {
+        for (nicks, nock) in [
+            (0, "0"),
+            (65_536, "1"),
+            (98_304, "1.5"),
+            (32_768, "0.5"),
+            (16_384, "0.25"),
+            (1, "0.0000152587890625"),
+            (65_537, "1.0000152587890625"),
+            (65_535, "0.9999847412109375"),
+            (u64::MAX, "281474976710655.9999847412109375"),
+        ] {
+            assert_eq!(Amount::from_nicks(nicks).to_string(), nock);
+        }
+    }
+
+    #[test]
+    fn arithmetic_is_checked() {
+        let max = Amount::from_nicks(u64::MAX);
+        let one = Amount::from_nicks(1);
+        assert_eq!(one.checked_add(one), Some(Amount::from_nicks(2)));
+        assert_eq!(max.checked_add(one), None);
+        assert_eq!(one.checked_sub(one), Some(Amount::ZERO));
+        assert_eq!(Amount::ZERO.checked_sub(one), None);
+        assert_eq!(one.checked_mul(3), Some(Amount::from_nicks(3)));
+        assert_eq!(max.checked_mul(2), None);
+        assert_eq!(
+            Amount::checked_sum([one, one, one]),
+            Some(Amount::from_nicks(3))
+        );
+        assert_eq!(Amount::checked_sum([max, one]), None);
+        assert_eq!(Amount::checked_sum([]), Some(Amount::ZERO));
+    }
+
+    #[test]
+    fn serde_takes_strings_or_numbers() {
+        let amount = Amount::from_nicks(u64::MAX);
+        let json = serde_json::to_string(&amount).unwrap();
+        assert_eq!(json, "\"18446744073709551615\"");
+        assert_eq!(serde_json::from_str::<Amount>(&json).unwrap(), amount);
+        assert_eq!(
+            serde_json::from_str::<Amount>("65536").unwrap(),
+            Amount::from_nicks(65_536)
+        );
+        assert_eq!(
+            serde_json::from_str::<Amount>("\"0\"").unwrap(),
+            Amount::ZERO
+        );
+        for json in [
+            "-1",
+            "1.5",
+            "\"\"",
+            "\"-1\"",
+            "\"1.5\"",
+            "\"1.000\"",
+            "\" 1\"",
+            "\"18446744073709551616\"",
+            "null",
+            "true",
+        ] {
+            assert!(
+                serde_json::from_str::<Amount>(json).is_err(),
+                "{} is accepted",
+                json
+            );
+        }
+    }
+
+    #[test]
+    fn ud_serde_round_trips() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Note {
+            #[serde(with = "ud")]
+            assets: Amount,
+        }
+        let note = Note {
+            assets: Amount::from_nicks(1_234_567),
+        };
+        let json = serde_json::to_string(&note).unwrap();
+        assert_eq!(json, r#"{"assets":"1.234.567"}"#);
+        assert_eq!(serde_json::from_str::<Note>(&json).unwrap(), note);
+        assert!(serde_json::from_str::<Note>(r#"{"assets":"1234567"}"#).is_err());
+    }
+}
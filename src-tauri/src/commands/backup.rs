@@ -0,0 +1,43 @@
+use tauri::State;
+use tokio::sync::Mutex;
+
+use crate::keycrypt::Keycrypt;
+use crate::secret::{Secret, SecretString};
+use crate::seedphrase;
+use crate::shamir::{self, Share};
+
+/// Splits the seedphrase of `wallet_name` into `shares` shares, any `threshold` of which recover it.
+/// The shares together are the seed, so the vault password is asked for again.
+#[tauri::command]
+pub async fn backup_split(
+    state: State<'_, Mutex<Keycrypt>>,
+    wallet_name: String,
+    password: SecretString,
+    threshold: u8,
+    shares: u8,
+) -> Result<Vec<Share>, String> {
+    let mut keycrypt = state.lock().await;
+    keycrypt.verify_password(password.expose())?;
+    let seedphrase = keycrypt.get_seedphrase(wallet_name)?;
+    shamir::split(&seedphrase, threshold, shares)
+}
+
+/// Recovers a seedphrase from its shares and stores it in the vault as `wallet_name`.
+#[tauri::command]
+pub async fn backup_recover(
+    state: State<'_, Mutex<Keycrypt>>,
+    wallet_name: String,
+    shares: Vec<Share>,
+) -> Result<(), String> {
+    let seedphrase = shamir::recover(&shares)?;
+    let words = Secret::new(
+        seedphrase
+            .expose()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<String>>(),
+    );
+    seedphrase::validate(words.expose())?;
+    let mut keycrypt = state.lock().await;
+    keycrypt.add_wallet(wallet_name, seedphrase)
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+use crate::keycrypt::Keycrypt;
+use crate::manager::Wallet;
+
+/// Idle time after which the vault is locked, unless configured otherwise.
+const DEFAULT_AUTO_LOCK: Duration = Duration::from_secs(15 * 60);
+
+/// Shortest idle time allowed. The frontend reports activity at most every 30 seconds,
+/// so a shorter timeout would lock out a user who is working.
+const MIN_AUTO_LOCK: Duration = Duration::from_secs(60);
+
+/// Event emitted whenever the vault is locked, so the frontend can return to the password screen.
+pub const VAULT_LOCKED_EVENT: &str = "vault_locked";
+
+// Define the state for the inactivity timer
+pub struct AutoLockState {
+    timeout: Option<Duration>,
+    last_activity: Instant,
+    file_path: PathBuf,
+}
+
+impl AutoLockState {
+    pub fn new(app_handle: &AppHandle) -> Self {
+        let data_dir = app_handle
+            .path()
+            .app_data_dir()
+            .expect("Failed to get app data dir for AutoLockState");
+        let file_path = data_dir.join("AutoLock");
+        let timeout = Self::load_timeout(&file_path);
+        info!("[AutoLockState] Initializing. Timeout: {:?}", timeout);
+        AutoLockState {
+            timeout,
+            last_activity: Instant::now(),
+            file_path,
+        }
+    }
+
+    // The file holds the timeout in seconds, or "off" if auto-lock is disabled. Timeouts
+    // stored before the minimum existed are raised to it
+    fn load_timeout(file_path: &Path) -> Option<Duration> {
+        let Ok(content) = fs::read_to_string(file_path) else {
+            return Some(DEFAULT_AUTO_LOCK);
+        };
+        match content.trim() {
+            "off" => None,
+            secs => match secs.parse::<u64>() {
+                Ok(secs) => Some(Duration::from_secs(secs).max(MIN_AUTO_LOCK)),
+                Err(e) => {
+                    error!(
+                        "[AutoLockState] Failed to parse timeout '{}': {}. Using default.",
+                        secs, e
+                    );
+                    Some(DEFAULT_AUTO_LOCK)
+                }
+            },
+        }
+    }
+
+    /// Records user activity, restarting the idle timer.
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    /// Returns true if auto-lock is enabled and the timeout has passed since the last activity.
+    pub fn is_idle(&self) -> bool {
+        match self.timeout {
+            Some(timeout) => self.last_activity.elapsed() >= timeout,
+            None => false,
+        }
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<(), String> {
+        let content = match timeout {
+            Some(timeout) => timeout.as_secs().to_string(),
+            None => "off".to_string(),
+        };
+        fs::write(&self.file_path, content).map_err(|e| {
+            error!(
+                "[AutoLockState] Failed to write timeout to {:?}: {}",
+                self.file_path, e
+            );
+            e.to_string()
+        })?;
+        self.timeout = timeout;
+        self.touch();
+        Ok(())
+    }
+}
+
+/// Clears the decrypted vault, unloads the active wallet and wipes its kernel state,
+/// then notifies the frontend.
+pub async fn lock_session(app_handle: &AppHandle) -> Result<(), String> {
+    let vault = app_handle.state::<Mutex<Keycrypt>>();
+    let wallet = app_handle.state::<Mutex<Wallet>>();
+
+    let mut vault = vault.lock().await;
+    vault.lock();
+    let mut wallet = wallet.lock().await;
+    let res = wallet.unload().await;
+
+    info!("[AutoLock] Vault locked");
+    if let Err(e) = app_handle.emit(VAULT_LOCKED_EVENT, ()) {
+        error!("[AutoLock] Failed to emit {}: {}", VAULT_LOCKED_EVENT, e);
+    }
+    res
+}
+
+#[tauri::command]
+pub async fn vault_lock(app: AppHandle) -> Result<(), String> {
+    lock_session(&app).await
+}
+
+#[tauri::command]
+pub async fn session_touch(state: State<'_, Mutex<AutoLockState>>) -> Result<(), String> {
+    state.lock().await.touch();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn auto_lock_timeout(
+    state: State<'_, Mutex<AutoLockState>>,
+) -> Result<Option<u64>, String> {
+    Ok(state.lock().await.timeout().map(|t| t.as_secs()))
+}
+
+#[tauri::command]
+pub async fn set_auto_lock_timeout(
+    state: State<'_, Mutex<AutoLockState>>,
+    seconds: Option<u64>,
+) -> Result<(), String> {
+    if let Some(seconds) = seconds {
+        if seconds < MIN_AUTO_LOCK.as_secs() {
+            return Err(format!(
+                "auto-lock timeout must be at least {} seconds",
+                MIN_AUTO_LOCK.as_secs()
+            ));
+        }
+    }
+    let mut state = state.lock().await;
+    state.set_timeout(seconds.map(Duration::from_secs))
+}
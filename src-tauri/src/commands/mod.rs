@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 use tauri::State;
 use tokio::sync::Mutex;
 
+pub mod backup;
+pub mod lock;
 pub mod nockchain_node;
 pub mod terms;
 pub mod updater;
@@ -14,10 +16,14 @@ pub mod wallet;
 pub struct AeroeStatus {
     pub vault_exists: bool,
     pub vault_loaded: bool,
+    pub vault_backups: usize, // previous vault generations available for recovery
+    pub unlock_retry_in: Option<u64>, // seconds until another password attempt is accepted
     pub master_node_running: bool, // nockchain node (doesn't mine)
     pub block_height: Option<u32>,
-    pub num_miners: u64,      // total number of nodes mining
-    pub wallets: Vec<String>, // list of wallet names
+    pub num_miners: u64,                 // total number of nodes mining
+    pub wallets: Vec<String>,            // list of wallet names
+    pub watch_only_wallets: Vec<String>, // wallets stored by master pubkey only
+    pub multisig_wallets: Vec<String>,   // wallets tracking the notes of a key set
     pub active_wallet: Option<String>,
 }
 
@@ -41,10 +47,14 @@ pub async fn aeroe_status(
     let status = AeroeStatus {
         vault_exists: vault.vault_exists(),
         vault_loaded: vault.is_loaded(),
+        vault_backups: vault.backup_count(),
+        unlock_retry_in: vault.unlock_retry_in(),
         master_node_running: master_running,
         block_height: wallet.get_block_height(),
         num_miners: num_workers,
         wallets: vault.get_wallets(),
+        watch_only_wallets: vault.get_watch_only_wallets(),
+        multisig_wallets: vault.get_multisig_wallets(),
         active_wallet: wallet.get_active_wallet(),
     };
     Ok(status)
@@ -2,38 +2,153 @@ use tauri::State;
 use tokio::sync::Mutex;
 
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
 
-use crate::keycrypt::Keycrypt;
-use crate::manager;
+use crate::amount::Amount;
+use crate::commands::lock::AutoLockState;
+use crate::keycrypt::{ImportConflict, ImportReport, KdfParams, Keycrypt};
+use crate::manager::coin_select::{CoinSelection, CoinSelector, Explicit};
+use crate::manager::fee::{FeeEstimate, FeeSpec};
+use crate::manager::inspect::DraftContents;
+use crate::manager::{self, history};
+use crate::pubkey::{Lock, Pubkey};
+use crate::secret::{Secret, SecretString};
+use crate::seedphrase;
 
 #[tauri::command]
 pub async fn vault_create(
     state: State<'_, Mutex<Keycrypt>>,
-    password: String,
+    auto_lock: State<'_, Mutex<AutoLockState>>,
+    password: SecretString,
+    kdf_params: Option<KdfParams>,
 ) -> Result<(), String> {
     let mut keycrypt = state.lock().await;
-    keycrypt.create(password)
+    keycrypt.create(password, kdf_params)?;
+    auto_lock.lock().await.touch();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn vault_load(
+    state: State<'_, Mutex<Keycrypt>>,
+    auto_lock: State<'_, Mutex<AutoLockState>>,
+    password: SecretString,
+) -> Result<(), String> {
+    let mut keycrypt = state.lock().await;
+    keycrypt.load(password)?;
+    auto_lock.lock().await.touch();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn vault_recover(
+    state: State<'_, Mutex<Keycrypt>>,
+    auto_lock: State<'_, Mutex<AutoLockState>>,
+    password: SecretString,
+) -> Result<usize, String> {
+    let mut keycrypt = state.lock().await;
+    let generation = keycrypt.recover(password)?;
+    auto_lock.lock().await.touch();
+    Ok(generation)
 }
 
 #[tauri::command]
-pub async fn vault_load(state: State<'_, Mutex<Keycrypt>>, password: String) -> Result<(), String> {
+pub async fn vault_change_password(
+    state: State<'_, Mutex<Keycrypt>>,
+    old_password: SecretString,
+    new_password: SecretString,
+    kdf_params: Option<KdfParams>,
+) -> Result<(), String> {
     let mut keycrypt = state.lock().await;
-    keycrypt.load(password)
+    keycrypt.change_password(old_password, new_password, kdf_params)
+}
+
+#[tauri::command]
+pub async fn vault_calibrate_kdf(target_ms: u64) -> Result<KdfParams, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        KdfParams::calibrate(Duration::from_millis(target_ms))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn vault_export(
+    state: State<'_, Mutex<Keycrypt>>,
+    export_passphrase: SecretString,
+    path: String,
+    wallet_names: Option<Vec<String>>,
+) -> Result<usize, String> {
+    let keycrypt = state.lock().await;
+    keycrypt.export(export_passphrase, wallet_names, &PathBuf::from(path))
+}
+
+#[tauri::command]
+pub async fn vault_import(
+    state: State<'_, Mutex<Keycrypt>>,
+    export_passphrase: SecretString,
+    path: String,
+    on_conflict: Option<ImportConflict>,
+) -> Result<ImportReport, String> {
+    let mut keycrypt = state.lock().await;
+    keycrypt.import(
+        export_passphrase,
+        &PathBuf::from(path),
+        on_conflict.unwrap_or_default(),
+    )
 }
 
 #[tauri::command]
 pub async fn wallet_create(
     state: State<'_, Mutex<Keycrypt>>,
     wallet_name: String,
-    seedphrase: Vec<String>,
+    seedphrase: Secret<Vec<String>>,
 ) -> Result<(), String> {
+    let words = Secret::new(seedphrase::normalize(seedphrase.expose()));
+    seedphrase::validate(words.expose())?;
     let mut keycrypt = state.lock().await;
-    keycrypt.add_wallet(wallet_name, seedphrase.join(" "))
+    keycrypt.add_wallet(wallet_name, SecretString::new(words.expose().join(" ")))
+}
+
+#[tauri::command]
+pub async fn wallet_create_watch_only(
+    state: State<'_, Mutex<Keycrypt>>,
+    wallet_name: String,
+    master_pubkey: String,
+) -> Result<(), String> {
+    let master_pubkey = Pubkey::parse(&master_pubkey)?;
+    let mut keycrypt = state.lock().await;
+    keycrypt.add_watch_only(wallet_name, master_pubkey.to_string())
+}
+
+#[tauri::command]
+pub async fn wallet_create_multisig(
+    state: State<'_, Mutex<Keycrypt>>,
+    wallet_name: String,
+    threshold: u64,
+    pubkeys: Vec<String>,
+) -> Result<(), String> {
+    let pubkeys = pubkeys
+        .iter()
+        .map(|pubkey| Pubkey::parse(pubkey))
+        .collect::<Result<Vec<Pubkey>, String>>()?;
+    let lock = Lock::multisig(threshold, pubkeys)?;
+    let mut keycrypt = state.lock().await;
+    keycrypt.add_multisig(wallet_name, lock)
+}
+
+#[tauri::command]
+pub async fn seedphrase_validate(
+    seedphrase: Secret<Vec<String>>,
+) -> Result<seedphrase::SeedphraseCheck, String> {
+    let words = Secret::new(seedphrase::normalize(seedphrase.expose()));
+    Ok(seedphrase::check(words.expose()))
 }
 #[tauri::command]
 pub async fn keygen(
     wallet: tauri::State<'_, Mutex<manager::Wallet>>,
-) -> Result<Vec<String>, String> {
+) -> Result<Secret<Vec<String>>, String> {
     let wallet_lock = wallet.lock().await;
     wallet_lock.keygen().await?;
     let seedphrase = wallet_lock.peek_seedphrase().await?;
@@ -45,17 +160,72 @@ pub async fn keygen(
 pub async fn wallet_load(
     wallet: tauri::State<'_, Mutex<manager::Wallet>>,
     vault: tauri::State<'_, Mutex<Keycrypt>>,
+    auto_lock: tauri::State<'_, Mutex<AutoLockState>>,
     wallet_name: String,
 ) -> Result<(), String> {
+    auto_lock.lock().await.touch();
     let vault_lock = vault.lock().await;
-    let seedphrase = vault_lock.get_seedphrase(wallet_name.clone())?;
-    tracing::debug!("seedphrase: {:?}", seedphrase);
     let mut wallet_lock = wallet.lock().await;
-    wallet_lock.gen_master_privkey(seedphrase).await?;
+    if let Some(lock) = vault_lock.get_multisig_lock(wallet_name.clone())? {
+        wallet_lock.unload().await?;
+        return wallet_lock.load_multisig(wallet_name, lock).await;
+    }
+    if let Some(master_pubkey) = vault_lock.get_watch_only_pubkey(wallet_name.clone())? {
+        // drop the private key a previously loaded wallet left in the kernel
+        wallet_lock.unload().await?;
+        return wallet_lock
+            .load_watch_only(wallet_name, master_pubkey)
+            .await;
+    }
+    let seedphrase = vault_lock.get_seedphrase(wallet_name.clone())?;
+    wallet_lock.gen_master_privkey(&seedphrase).await?;
     wallet_lock.load(wallet_name).await?;
     Ok(())
 }
 
+#[tauri::command]
+pub async fn wallet_rename(
+    wallet: tauri::State<'_, Mutex<manager::Wallet>>,
+    vault: tauri::State<'_, Mutex<Keycrypt>>,
+    old_name: String,
+    new_name: String,
+) -> Result<(), String> {
+    let mut vault_lock = vault.lock().await;
+    let wallet_lock = wallet.lock().await;
+    if wallet_lock.get_active_wallet().as_ref() == Some(&old_name) {
+        return Err("cannot rename the loaded wallet".to_string());
+    }
+    // move the drafts and history first so a failure leaves the vault untouched
+    wallet_lock.rename_wallet_files(&old_name, &new_name)?;
+    if let Err(e) = vault_lock.rename_wallet(old_name.clone(), new_name.clone()) {
+        if let Err(undo) = wallet_lock.rename_wallet_files(&new_name, &old_name) {
+            tracing::error!(
+                "wallet_rename: failed to restore files of {}: {}",
+                old_name,
+                undo
+            );
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn wallet_remove(
+    wallet: tauri::State<'_, Mutex<manager::Wallet>>,
+    vault: tauri::State<'_, Mutex<Keycrypt>>,
+    wallet_name: String,
+    password: SecretString,
+) -> Result<(), String> {
+    let mut vault_lock = vault.lock().await;
+    let wallet_lock = wallet.lock().await;
+    if wallet_lock.get_active_wallet().as_ref() == Some(&wallet_name) {
+        return Err("cannot remove the loaded wallet".to_string());
+    }
+    vault_lock.remove_wallet(wallet_name.clone(), password)?;
+    wallet_lock.remove_wallet_files(&wallet_name)
+}
+
 #[tauri::command]
 pub async fn master_pubkey(
     wallet: tauri::State<'_, Mutex<manager::Wallet>>,
@@ -73,7 +243,7 @@ pub async fn master_pubkey(
 pub async fn balance(
     wallet: tauri::State<'_, Mutex<manager::Wallet>>,
     wallet_name: String,
-) -> Result<u64, String> {
+) -> Result<Amount, String> {
     let wallet_lock = wallet.lock().await;
     let loaded_wallet_name = wallet_lock.get_active_wallet();
     if loaded_wallet_name != Some(wallet_name) {
@@ -87,14 +257,72 @@ pub async fn create_tx(
     wallet: tauri::State<'_, Mutex<manager::Wallet>>,
     wallet_name: String,
     transactions: Vec<manager::TransactionEntry>,
-    fee: u64,
+    fee: FeeSpec,
+    coin_selection: Option<CoinSelection>,
+    note_names: Option<Vec<String>>,
+    allow_self_send: Option<bool>,
 ) -> Result<manager::NockchainTxMeta, String> {
     let mut wallet_lock = wallet.lock().await;
     let loaded_wallet_name = wallet_lock.get_active_wallet();
     if loaded_wallet_name != Some(wallet_name) {
         return Err("wallet name mismatch".to_string());
     }
-    wallet_lock.create_tx(transactions, fee).await
+    let selector = note_selector(coin_selection, note_names)?;
+    wallet_lock
+        .create_tx(
+            transactions,
+            fee,
+            selector.as_ref(),
+            allow_self_send.unwrap_or(false),
+        )
+        .await
+}
+
+#[tauri::command]
+pub async fn estimate_fee(
+    wallet: tauri::State<'_, Mutex<manager::Wallet>>,
+    wallet_name: String,
+    transactions: Vec<manager::TransactionEntry>,
+    coin_selection: Option<CoinSelection>,
+    note_names: Option<Vec<String>>,
+) -> Result<FeeEstimate, String> {
+    let wallet_lock = wallet.lock().await;
+    let loaded_wallet_name = wallet_lock.get_active_wallet();
+    if loaded_wallet_name != Some(wallet_name) {
+        return Err("wallet name mismatch".to_string());
+    }
+    let selector = note_selector(coin_selection, note_names)?;
+    wallet_lock
+        .estimate_fee(&transactions, selector.as_ref())
+        .await
+}
+
+// the user's notes if they picked them, the selection strategy otherwise
+fn note_selector(
+    coin_selection: Option<CoinSelection>,
+    note_names: Option<Vec<String>>,
+) -> Result<Box<dyn CoinSelector>, String> {
+    match (note_names, coin_selection) {
+        (Some(_), Some(_)) => {
+            Err("pass either note names or a coin selection strategy".to_string())
+        }
+        (Some(names), None) => Ok(Box::new(Explicit::new(names))),
+        (None, strategy) => Ok(strategy.unwrap_or_default().selector()),
+    }
+}
+
+#[tauri::command]
+pub async fn inspect_draft(
+    wallet: tauri::State<'_, Mutex<manager::Wallet>>,
+    wallet_name: String,
+    draft_id: String,
+) -> Result<DraftContents, String> {
+    let wallet_lock = wallet.lock().await;
+    let loaded_wallet_name = wallet_lock.get_active_wallet();
+    if loaded_wallet_name != Some(wallet_name) {
+        return Err("wallet name mismatch".to_string());
+    }
+    wallet_lock.inspect_draft(&draft_id)
 }
 
 #[tauri::command]
@@ -111,6 +339,24 @@ pub async fn sign_tx(
     wallet_lock.sign_tx(draft_id).await
 }
 
+#[tauri::command]
+pub async fn cosign_tx(
+    wallet: tauri::State<'_, Mutex<manager::Wallet>>,
+    vault: tauri::State<'_, Mutex<Keycrypt>>,
+    wallet_name: String,
+    draft_id: String,
+    cosigner_wallet_name: String,
+) -> Result<manager::NockchainTxMeta, String> {
+    let vault_lock = vault.lock().await;
+    let mut wallet_lock = wallet.lock().await;
+    let loaded_wallet_name = wallet_lock.get_active_wallet();
+    if loaded_wallet_name != Some(wallet_name) {
+        return Err("wallet name mismatch".to_string());
+    }
+    let seedphrase = vault_lock.get_seedphrase(cosigner_wallet_name)?;
+    wallet_lock.cosign_tx(draft_id, &seedphrase).await
+}
+
 #[tauri::command]
 pub async fn send_tx(
     wallet: tauri::State<'_, Mutex<manager::Wallet>>,
@@ -125,6 +371,94 @@ pub async fn send_tx(
     wallet_lock.send_tx(draft_id).await
 }
 
+#[tauri::command]
+pub async fn cancel_tx(
+    wallet: tauri::State<'_, Mutex<manager::Wallet>>,
+    wallet_name: String,
+    draft_id: String,
+) -> Result<(), String> {
+    let mut wallet_lock = wallet.lock().await;
+    let loaded_wallet_name = wallet_lock.get_active_wallet();
+    if loaded_wallet_name != Some(wallet_name) {
+        return Err("wallet name mismatch".to_string());
+    }
+    wallet_lock.cancel_tx(&draft_id)
+}
+
+#[tauri::command]
+pub async fn export_draft(
+    wallet: tauri::State<'_, Mutex<manager::Wallet>>,
+    wallet_name: String,
+    draft_id: String,
+    path: String,
+) -> Result<(), String> {
+    let wallet_lock = wallet.lock().await;
+    let loaded_wallet_name = wallet_lock.get_active_wallet();
+    if loaded_wallet_name != Some(wallet_name) {
+        return Err("wallet name mismatch".to_string());
+    }
+    wallet_lock.export_draft(&draft_id, &PathBuf::from(path))
+}
+
+#[tauri::command]
+pub async fn import_draft(
+    wallet: tauri::State<'_, Mutex<manager::Wallet>>,
+    wallet_name: String,
+    path: String,
+) -> Result<manager::NockchainTxMeta, String> {
+    let mut wallet_lock = wallet.lock().await;
+    let loaded_wallet_name = wallet_lock.get_active_wallet();
+    if loaded_wallet_name != Some(wallet_name) {
+        return Err("wallet name mismatch".to_string());
+    }
+    wallet_lock.import_draft(&PathBuf::from(path))
+}
+
+#[tauri::command]
+pub async fn import_signed(
+    wallet: tauri::State<'_, Mutex<manager::Wallet>>,
+    wallet_name: String,
+    path: String,
+) -> Result<manager::NockchainTxMeta, String> {
+    let mut wallet_lock = wallet.lock().await;
+    let loaded_wallet_name = wallet_lock.get_active_wallet();
+    if loaded_wallet_name != Some(wallet_name) {
+        return Err("wallet name mismatch".to_string());
+    }
+    wallet_lock.import_signed(&PathBuf::from(path))
+}
+
+#[tauri::command]
+pub async fn list_notes(
+    wallet: tauri::State<'_, Mutex<manager::Wallet>>,
+    wallet_name: String,
+) -> Result<Vec<manager::NoteInfo>, String> {
+    let wallet_lock = wallet.lock().await;
+    let loaded_wallet_name = wallet_lock.get_active_wallet();
+    if loaded_wallet_name != Some(wallet_name) {
+        return Err("wallet name mismatch".to_string());
+    }
+    wallet_lock.list_notes().await
+}
+
+#[tauri::command]
+pub async fn tx_history(
+    wallet: tauri::State<'_, Mutex<manager::Wallet>>,
+    wallet_name: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<history::HistoryPage, String> {
+    let wallet_lock = wallet.lock().await;
+    let loaded_wallet_name = wallet_lock.get_active_wallet();
+    if loaded_wallet_name != Some(wallet_name) {
+        return Err("wallet name mismatch".to_string());
+    }
+    let limit = limit
+        .unwrap_or(history::DEFAULT_PAGE_SIZE)
+        .min(history::MAX_PAGE_SIZE);
+    wallet_lock.tx_history(offset.unwrap_or(0), limit)
+}
+
 #[tauri::command]
 pub async fn list_unsent_txs(
     wallet: tauri::State<'_, Mutex<manager::Wallet>>,
@@ -0,0 +1,377 @@
+// decode.rs
+//
+// Typed decoding of nouns returned by the kernels. `FromNoun` turns a noun into a
+// Rust value, failing with a `DecodeError` that says what was expected and where.
+// Atoms decode to integers, `~` to `()`, cords to strings, JSON cords to anything
+// deserializable, Hoon lists to vectors, units to options and trees (z-sets and
+// z-maps) to `Tree`. Peek results, which come wrapped as `[~ ~ value]`, are
+// unwrapped with `peek`.
+
+use std::fmt;
+
+use nockapp::noun::slab::NounSlab;
+use nockvm::noun::Noun;
+use serde::de::DeserializeOwned;
+
+#[derive(Debug)]
+pub enum DecodeError {
+    NotAtom,
+    NotCell,
+    /// an atom that doesn't fit in the requested integer type
+    TooLarge(&'static str),
+    /// a unit or list terminator that isn't `~`
+    NotNull,
+    NotUtf8(std::str::Utf8Error),
+    Json(serde_json::Error),
+    /// a noun of the right shape that doesn't hold a valid value
+    Invalid(String),
+    /// the peek path doesn't exist, `~`
+    PeekFailed,
+    /// the peek returned nothing, `[~ ~]`
+    PeekEmpty,
+    /// an error within an element of a list, a cell or a unit
+    At(String, Box<DecodeError>),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::NotAtom => write!(f, "expected an atom, got a cell"),
+            DecodeError::NotCell => write!(f, "expected a cell, got an atom"),
+            DecodeError::TooLarge(ty) => write!(f, "atom does not fit in {}", ty),
+            DecodeError::NotNull => write!(f, "expected ~"),
+            DecodeError::NotUtf8(e) => write!(f, "cord is not valid UTF-8: {}", e),
+            DecodeError::Json(e) => write!(f, "cord is not valid JSON: {}", e),
+            DecodeError::Invalid(e) => write!(f, "{}", e),
+            DecodeError::PeekFailed => write!(f, "peek failed"),
+            DecodeError::PeekEmpty => write!(f, "peek returned nothing"),
+            DecodeError::At(location, e) => write!(f, "{}: {}", location, e),
+        }
+    }
+}
+
+impl DecodeError {
+    pub fn at(self, location: impl Into<String>) -> Self {
+        DecodeError::At(location.into(), Box::new(self))
+    }
+}
+
+pub trait FromNoun: Sized {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError>;
+}
+
+/// Decodes the value of a peek result, `[~ ~ value]`.
+pub fn peek<T: FromNoun>(slab: &NounSlab) -> Result<T, DecodeError> {
+    let root = unsafe { slab.root() };
+    match Option::<Option<T>>::from_noun(root)? {
+        Some(Some(value)) => Ok(value),
+        Some(None) => Err(DecodeError::PeekEmpty),
+        None => Err(DecodeError::PeekFailed),
+    }
+}
+
+impl FromNoun for Noun {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        Ok(*noun)
+    }
+}
+
+impl FromNoun for u64 {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        noun.as_atom()
+            .map_err(|_| DecodeError::NotAtom)?
+            .as_u64()
+            .map_err(|_| DecodeError::TooLarge("u64"))
+    }
+}
+
+impl FromNoun for u32 {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let value = u64::from_noun(noun).map_err(|e| match e {
+            DecodeError::TooLarge(_) => DecodeError::TooLarge("u32"),
+            e => e,
+        })?;
+        u32::try_from(value).map_err(|_| DecodeError::TooLarge("u32"))
+    }
+}
+
+/// Null, `~`.
+impl FromNoun for () {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        null(noun)
+    }
+}
+
+/// A cord, the bytes of the atom as UTF-8.
+impl FromNoun for String {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let atom = noun.as_atom().map_err(|_| DecodeError::NotAtom)?;
+        let bytes = atom.as_ne_bytes();
+        // the atom is padded with zero bytes to a whole word
+        let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+        std::str::from_utf8(&bytes[..len])
+            .map(str::to_string)
+            .map_err(DecodeError::NotUtf8)
+    }
+}
+
+/// A cord holding JSON.
+#[derive(Debug)]
+pub struct Json<T>(pub T);
+
+impl<T: DeserializeOwned> FromNoun for Json<T> {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let cord = String::from_noun(noun)?;
+        serde_json::from_str(&cord)
+            .map(Json)
+            .map_err(DecodeError::Json)
+    }
+}
+
+/// A unit, `~` or `[~ value]`.
+impl<T: FromNoun> FromNoun for Option<T> {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        if noun.is_atom() {
+            null(noun)?;
+            return Ok(None);
+        }
+        let cell = noun.as_cell().map_err(|_| DecodeError::NotCell)?;
+        null(&cell.head()).map_err(|e| e.at("unit head"))?;
+        T::from_noun(&cell.tail())
+            .map(Some)
+            .map_err(|e| e.at("unit value"))
+    }
+}
+
+/// A null-terminated Hoon list.
+impl<T: FromNoun> FromNoun for Vec<T> {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let mut items = Vec::new();
+        let mut rest = *noun;
+        while let Ok(cell) = rest.as_cell() {
+            let item = T::from_noun(&cell.head())
+                .map_err(|e| e.at(format!("list item {}", items.len())))?;
+            items.push(item);
+            rest = cell.tail();
+        }
+        null(&rest).map_err(|e| e.at("list end"))?;
+        Ok(items)
+    }
+}
+
+/// The items of a Hoon tree, `~` or `[n l r]`, the way z-sets and z-maps are
+/// stored. The entries of a map decode as `(key, value)`.
+#[derive(Debug)]
+pub struct Tree<T>(pub Vec<T>);
+
+impl<T: FromNoun> FromNoun for Tree<T> {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let mut items = Vec::new();
+        let mut nodes = vec![*noun];
+        while let Some(node) = nodes.pop() {
+            if node.is_atom() {
+                null(&node).map_err(|e| e.at("tree leaf"))?;
+                continue;
+            }
+            let (item, (left, right)) = <(Noun, (Noun, Noun))>::from_noun(&node)?;
+            let item =
+                T::from_noun(&item).map_err(|e| e.at(format!("tree item {}", items.len())))?;
+            items.push(item);
+            nodes.push(right);
+            nodes.push(left);
+        }
+        Ok(Tree(items))
+    }
+}
+
+impl<A: FromNoun, B: FromNoun> FromNoun for (A, B) {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let cell = noun.as_cell().map_err(|_| DecodeError::NotCell)?;
+        let head = A::from_noun(&cell.head()).map_err(|e| e.at("head"))?;
+        let tail = B::from_noun(&cell.tail()).map_err(|e| e.at("tail"))?;
+        Ok((head, tail))
+    }
+}
+
+fn null(noun: &Noun) -> Result<(), DecodeError> {
+    match u64::from_noun(noun) {
+        Ok(0) => Ok(()),
+        Err(DecodeError::NotAtom) => Err(DecodeError::NotAtom),
+        _ => Err(DecodeError::NotNull),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nockapp::AtomExt;
+    use nockvm::noun::{Atom, D, T};
+
+    use super::*;
+
+    fn cord(slab: &mut NounSlab, text: &str) -> Noun {
+        Atom::from_value(slab, text).unwrap().as_noun()
+    }
+
+    // the error inside the locations of `error`, with the locations outermost first
+    fn unwrap_at(mut error: DecodeError) -> (Vec<String>, DecodeError) {
+        let mut locations = Vec::new();
+        while let DecodeError::At(location, inner) = error {
+            locations.push(location);
+            error = *inner;
+        }
+        (locations, error)
+    }
+
+    #[test]
+    fn integers_reject_atoms_too_large() {
+        let mut slab = NounSlab::new();
+        assert_eq!(u32::from_noun(&D(u32::MAX as u64)).unwrap(), u32::MAX);
+        assert!(matches!(
+            u32::from_noun(&D(1 << 32)),
+            Err(DecodeError::TooLarge("u32"))
+        ));
+
+        // nine bytes, one more than a u64 holds
+        let large = cord(&mut slab, "ninebytes");
+        assert!(matches!(
+            u64::from_noun(&large),
+            Err(DecodeError::TooLarge("u64"))
+        ));
+        assert!(matches!(
+            u32::from_noun(&large),
+            Err(DecodeError::TooLarge("u32"))
+        ));
+
+        let cell = T(&mut slab, &[D(1), D(2)]);
+        assert!(matches!(u64::from_noun(&cell), Err(DecodeError::NotAtom)));
+    }
+
+    #[test]
+    fn cords_drop_trailing_zero_bytes() {
+        let mut slab = NounSlab::new();
+        let padded = cord(&mut slab, "abc\0\0");
+        assert_eq!(String::from_noun(&padded).unwrap(), "abc");
+        // a whole word, with no padding to drop
+        let word = cord(&mut slab, "abcdefgh");
+        assert_eq!(String::from_noun(&word).unwrap(), "abcdefgh");
+        let long = cord(&mut slab, "longer than a single word");
+        assert_eq!(
+            String::from_noun(&long).unwrap(),
+            "longer than a single word"
+        );
+        assert_eq!(String::from_noun(&D(0)).unwrap(), "");
+        assert!(matches!(
+            String::from_noun(&D(0xff)),
+            Err(DecodeError::NotUtf8(_))
+        ));
+    }
+
+    #[test]
+    fn json_cords_deserialize() {
+        let mut slab = NounSlab::new();
+        let json = cord(&mut slab, r#"{"a": [1, 2]}"#);
+        let Json(value) =
+            Json::<std::collections::HashMap<String, Vec<u64>>>::from_noun(&json).unwrap();
+        assert_eq!(value["a"], vec![1, 2]);
+
+        let not_json = cord(&mut slab, "{a: 1");
+        assert!(matches!(
+            Json::<serde_json::Value>::from_noun(&not_json),
+            Err(DecodeError::Json(_))
+        ));
+        let wrong_type = cord(&mut slab, r#""text""#);
+        assert!(matches!(
+            Json::<u64>::from_noun(&wrong_type),
+            Err(DecodeError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn lists_need_a_null_terminator() {
+        let mut slab = NounSlab::new();
+        let list = T(&mut slab, &[D(1), D(2), D(3), D(0)]);
+        assert_eq!(Vec::<u64>::from_noun(&list).unwrap(), vec![1, 2, 3]);
+        assert!(Vec::<u64>::from_noun(&D(0)).unwrap().is_empty());
+
+        let improper = T(&mut slab, &[D(1), D(2), D(3)]);
+        let (locations, error) = unwrap_at(Vec::<u64>::from_noun(&improper).unwrap_err());
+        assert_eq!(locations, ["list end"]);
+        assert!(matches!(error, DecodeError::NotNull));
+
+        let pair = T(&mut slab, &[D(4), D(5)]);
+        let bad_item = T(&mut slab, &[D(1), pair, D(0)]);
+        let (locations, error) = unwrap_at(Vec::<u64>::from_noun(&bad_item).unwrap_err());
+        assert_eq!(locations, ["list item 1"]);
+        assert!(matches!(error, DecodeError::NotAtom));
+    }
+
+    #[test]
+    fn units_decode_to_options() {
+        let mut slab = NounSlab::new();
+        assert_eq!(Option::<u64>::from_noun(&D(0)).unwrap(), None);
+        let some = T(&mut slab, &[D(0), D(7)]);
+        assert_eq!(Option::<u64>::from_noun(&some).unwrap(), Some(7));
+
+        assert!(matches!(
+            Option::<u64>::from_noun(&D(1)),
+            Err(DecodeError::NotNull)
+        ));
+        let bad_head = T(&mut slab, &[D(1), D(7)]);
+        let (locations, error) = unwrap_at(Option::<u64>::from_noun(&bad_head).unwrap_err());
+        assert_eq!(locations, ["unit head"]);
+        assert!(matches!(error, DecodeError::NotNull));
+        let pair = T(&mut slab, &[D(1), D(2)]);
+        let bad_value = T(&mut slab, &[D(0), pair]);
+        let (locations, error) = unwrap_at(Option::<u64>::from_noun(&bad_value).unwrap_err());
+        assert_eq!(locations, ["unit value"]);
+        assert!(matches!(error, DecodeError::NotAtom));
+    }
+
+    #[test]
+    fn trees_decode_every_node() {
+        let mut slab = NounSlab::new();
+        assert!(Tree::<u64>::from_noun(&D(0)).unwrap().0.is_empty());
+
+        let left = T(&mut slab, &[D(1), D(0), D(0)]);
+        let right = T(&mut slab, &[D(3), D(0), D(0)]);
+        let tree = T(&mut slab, &[D(2), left, right]);
+        assert_eq!(Tree::<u64>::from_noun(&tree).unwrap().0, vec![2, 1, 3]);
+
+        let key = cord(&mut slab, "key");
+        let entry = T(&mut slab, &[key, D(9)]);
+        let map = T(&mut slab, &[entry, D(0), D(0)]);
+        assert_eq!(
+            Tree::<(String, u64)>::from_noun(&map).unwrap().0,
+            vec![("key".to_string(), 9)]
+        );
+
+        let bad_leaf = T(&mut slab, &[D(1), D(5), D(0)]);
+        let (locations, error) = unwrap_at(Tree::<u64>::from_noun(&bad_leaf).unwrap_err());
+        assert_eq!(locations, ["tree leaf"]);
+        assert!(matches!(error, DecodeError::NotNull));
+    }
+
+    #[test]
+    fn peek_unwraps_its_result() {
+        let mut slab = NounSlab::new();
+        let value = T(&mut slab, &[D(0), D(0), D(42)]);
+        slab.set_root(value);
+        assert_eq!(peek::<u64>(&slab).unwrap(), 42);
+
+        let mut slab = NounSlab::new();
+        slab.set_root(D(0));
+        assert!(matches!(peek::<u64>(&slab), Err(DecodeError::PeekFailed)));
+
+        let mut slab = NounSlab::new();
+        let empty = T(&mut slab, &[D(0), D(0)]);
+        slab.set_root(empty);
+        assert!(matches!(peek::<u64>(&slab), Err(DecodeError::PeekEmpty)));
+
+        let mut slab = NounSlab::new();
+        let wrong = T(&mut slab, &[D(0), D(0), D(1), D(2)]);
+        slab.set_root(wrong);
+        let (locations, error) = unwrap_at(peek::<u64>(&slab).unwrap_err());
+        assert_eq!(locations, ["unit value", "unit value"]);
+        assert!(matches!(error, DecodeError::NotAtom));
+    }
+}
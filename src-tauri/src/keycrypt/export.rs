@@ -0,0 +1,149 @@
+// keycrypt/export.rs
+//
+// Portable backups of the vault. An export holds some or all wallets, encrypted
+// under a separate export passphrase with the vault's Argon2 parameters, so it can
+// be imported into the vault of another install.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::format::{self, Envelope, FileKind, VaultKey, VaultPayload};
+use super::{storage, validate_wallet_name, Keycrypt};
+use crate::secret::SecretString;
+
+/// What to do when an imported wallet has the same name as an existing one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportConflict {
+    /// keep the existing wallet and drop the imported one
+    #[default]
+    Skip,
+    /// replace the existing wallet with the imported one
+    Overwrite,
+    /// import under a new name, `name (2)`, `name (3)`, ...
+    Rename,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamedWallet {
+    pub from: String,
+    pub to: String,
+}
+
+/// Outcome of an import, per wallet name in the export.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub imported: Vec<String>,
+    pub overwritten: Vec<String>,
+    pub renamed: Vec<RenamedWallet>,
+    /// conflicting names that were skipped, and wallets already in the vault with the same seed
+    pub skipped: Vec<String>,
+}
+
+impl Keycrypt {
+    /// Writes the wallets named in `wallet_names` (or all of them if `None`) to `path`,
+    /// encrypted under `passphrase`. Returns the number of wallets exported.
+    pub fn export(
+        &self,
+        passphrase: SecretString,
+        wallet_names: Option<Vec<String>>,
+        path: &Path,
+    ) -> Result<usize, String> {
+        let Some(key) = &self.key else {
+            return Err("Vault not loaded".to_string());
+        };
+        let wallets = match wallet_names {
+            Some(names) => {
+                let mut wallets = HashMap::new();
+                for name in names {
+                    let Some(record) = self.data.get(&name) else {
+                        return Err(format!("Wallet {} not found", name));
+                    };
+                    wallets.insert(name, record.clone());
+                }
+                wallets
+            }
+            None => self.data.clone(),
+        };
+        if wallets.is_empty() {
+            return Err("No wallets to export".to_string());
+        }
+
+        let export_key = VaultKey::derive(passphrase.expose(), key.kdf())?;
+        let count = wallets.len();
+        let b64_payload =
+            format::encrypt(&VaultPayload { wallets }, &export_key, FileKind::Export)?;
+        storage::write_atomic(path, b64_payload.as_bytes())
+            .map_err(|e| format!("Failed to write export file: {}", e))?;
+        tracing::info!("export: wrote {} wallets to {:?}", count, path);
+        Ok(count)
+    }
+
+    /// Decrypts the export at `path` with `passphrase` and merges its wallets into the vault,
+    /// resolving duplicate names according to `on_conflict`.
+    pub fn import(
+        &mut self,
+        passphrase: SecretString,
+        path: &Path,
+        on_conflict: ImportConflict,
+    ) -> Result<ImportReport, String> {
+        if !self.loaded {
+            return Err("Vault not loaded".to_string());
+        }
+        let b64_payload =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read export file: {}", e))?;
+        let envelope = Envelope::parse(&b64_payload, FileKind::Export)?;
+        let export_key = envelope.derive_key(passphrase.expose())?;
+        let payload = envelope.decrypt(&export_key)?;
+
+        let mut report = ImportReport::default();
+        let mut data = self.data.clone();
+        let mut names = payload.wallets.keys().cloned().collect::<Vec<String>>();
+        names.sort();
+        for name in names {
+            validate_wallet_name(&name)
+                .map_err(|e| format!("Export has an invalid wallet name {:?}: {}", name, e))?;
+            let record = payload.wallets[&name].clone();
+            let Some(existing) = data.get(&name) else {
+                data.insert(name.clone(), record);
+                report.imported.push(name);
+                continue;
+            };
+            if existing.same_keys(&record) {
+                report.skipped.push(name);
+                continue;
+            }
+            match on_conflict {
+                ImportConflict::Skip => report.skipped.push(name),
+                ImportConflict::Overwrite => {
+                    data.insert(name.clone(), record);
+                    report.overwritten.push(name);
+                }
+                ImportConflict::Rename => {
+                    let new_name = (2..)
+                        .map(|i| format!("{} ({})", name, i))
+                        .find(|candidate| !data.contains_key(candidate))
+                        .unwrap();
+                    data.insert(new_name.clone(), record);
+                    report.renamed.push(RenamedWallet {
+                        from: name,
+                        to: new_name,
+                    });
+                }
+            }
+        }
+
+        let previous = std::mem::replace(&mut self.data, data);
+        if let Err(e) = self.write() {
+            self.data = previous;
+            return Err(e);
+        }
+        tracing::info!("import: {:?}", report);
+        Ok(report)
+    }
+}
@@ -0,0 +1,488 @@
+// keycrypt/format.rs
+//
+// On-disk encoding of the vault file, and of vault exports which share its layout
+// under a different magic ("AEROEEXP") and are never in the legacy format.
+//
+// Every version is stored as a single Base64 string. The decoded bytes are:
+//
+// v1 (legacy): "79CLOVER" ∥ salt ∥ nonce ∥ ciphertext
+//              plaintext is `name\tseedphrase` lines with escaped tabs/newlines
+//
+// v2:          "AEROEVLT" ∥ version ∥ salt ∥ nonce ∥ ciphertext
+//              plaintext is a JSON-serialized `VaultPayload`, and the header
+//              (magic ∥ version) is authenticated as associated data
+//
+// v3:          "AEROEVLT" ∥ version ∥ memory ∥ iterations ∥ parallelism ∥ salt ∥ nonce ∥ ciphertext
+//              as v2, with the Argon2 parameters stored as little-endian u32s in
+//              the (authenticated) header
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Cursor, Read};
+use std::time::{Duration, Instant};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::rngs::OsRng;
+use rand_core::TryRngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::pubkey::Lock;
+use crate::secret::{Secret, SecretString};
+use crate::timestamp::now_nanos;
+
+/// Magic header of the legacy (v1) tab-separated format.
+const LEGACY_MAGIC: &[u8] = b"79CLOVER"; // 8 bytes
+
+/// Magic header of the versioned formats (v2 and later).
+const HEADER_MAGIC: &[u8] = b"AEROEVLT"; // 8 bytes
+
+/// Magic header of vault exports.
+const EXPORT_MAGIC: &[u8] = b"AEROEEXP"; // 8 bytes
+
+/// Version written by `encrypt`. Bump whenever the header or payload changes.
+pub const FORMAT_VERSION: u8 = 3;
+
+/// Version reported for files in the legacy format.
+pub const LEGACY_VERSION: u8 = 1;
+
+/// First version that stores its Argon2 parameters in the header.
+const KDF_HEADER_VERSION: u8 = 3;
+
+/// Lengths (in bytes) for salt and nonce.
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Bounds accepted for Argon2 parameters, both from callers and from file headers.
+const MIN_MEMORY_KIB: u32 = 19 * 1024;
+const MAX_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 16;
+
+/// Calibration starts at this memory cost and never goes above the legacy default.
+const CALIBRATION_START_KIB: u32 = 32 * 1024;
+const CALIBRATION_MAX_KIB: u32 = 1024 * 1024;
+
+/// Argon2id cost parameters used to derive the vault key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// The parameters every vault used before they were stored in the header:
+    /// memory = 1024 * 1024 KiB = 1024 MiB, iterations = 8, parallelism = 1
+    fn default() -> Self {
+        Self {
+            memory_kib: 1024 * 1024,
+            iterations: 8,
+            parallelism: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Checks that the parameters are within the bounds we are willing to run.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_MEMORY_KIB..=MAX_MEMORY_KIB).contains(&self.memory_kib) {
+            return Err(format!(
+                "Argon2 memory must be between {} and {} KiB",
+                MIN_MEMORY_KIB, MAX_MEMORY_KIB
+            ));
+        }
+        if !(1..=MAX_ITERATIONS).contains(&self.iterations) {
+            return Err(format!(
+                "Argon2 iterations must be between 1 and {}",
+                MAX_ITERATIONS
+            ));
+        }
+        if !(1..=MAX_PARALLELISM).contains(&self.parallelism) {
+            return Err(format!(
+                "Argon2 parallelism must be between 1 and {}",
+                MAX_PARALLELISM
+            ));
+        }
+        Ok(())
+    }
+
+    /// Picks parameters so that one key derivation on this machine takes roughly `target`.
+    /// Memory is raised first (up to 1 GiB), then iterations make up the remaining time.
+    pub fn calibrate(target: Duration) -> Result<Self, String> {
+        let mut params = Self {
+            memory_kib: CALIBRATION_START_KIB,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let mut elapsed = params.measure()?;
+        while elapsed * 2 <= target && params.memory_kib * 2 <= CALIBRATION_MAX_KIB {
+            params.memory_kib *= 2;
+            elapsed = params.measure()?;
+        }
+        if elapsed < target {
+            let factor = target.as_secs_f64() / elapsed.as_secs_f64().max(f64::EPSILON);
+            params.iterations = (factor.floor() as u32).clamp(1, MAX_ITERATIONS);
+        }
+        tracing::info!("calibrated argon2 params for {:?}: {:?}", target, params);
+        Ok(params)
+    }
+
+    /// Times a single key derivation with these parameters.
+    fn measure(&self) -> Result<Duration, String> {
+        let start = Instant::now();
+        derive_key("calibration", &[0u8; SALT_LEN], self)?;
+        Ok(start.elapsed())
+    }
+
+    fn to_bytes(self) -> [u8; 12] {
+        let mut bytes = [0u8; 12];
+        bytes[0..4].copy_from_slice(&self.memory_kib.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.iterations.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.parallelism.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: [u8; 12]) -> Self {
+        let u32_at =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        Self {
+            memory_kib: u32_at(0),
+            iterations: u32_at(4),
+            parallelism: u32_at(8),
+        }
+    }
+}
+
+/// Decrypted contents of the vault.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VaultPayload {
+    pub wallets: HashMap<String, WalletRecord>,
+}
+
+/// A single wallet stored in the vault.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletRecord {
+    /// `None` for watch-only and multisig wallets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seedphrase: Option<SecretString>,
+    /// Only stored for watch-only wallets, the kernel derives it from the seedphrase otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub master_pubkey: Option<String>,
+    /// Only stored for multisig wallets, the key set whose notes the wallet tracks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<Lock>,
+    /// Unix timestamp in nanoseconds, unknown for wallets migrated from v1.
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+impl WalletRecord {
+    pub fn new(seedphrase: SecretString) -> Self {
+        Self {
+            seedphrase: Some(seedphrase),
+            master_pubkey: None,
+            multisig: None,
+            created_at: Some(now_nanos()),
+        }
+    }
+
+    pub fn watch_only(master_pubkey: String) -> Self {
+        Self {
+            seedphrase: None,
+            master_pubkey: Some(master_pubkey),
+            multisig: None,
+            created_at: Some(now_nanos()),
+        }
+    }
+
+    pub fn multisig(lock: Lock) -> Self {
+        Self {
+            seedphrase: None,
+            master_pubkey: None,
+            multisig: Some(lock),
+            created_at: Some(now_nanos()),
+        }
+    }
+
+    pub fn is_watch_only(&self) -> bool {
+        self.seedphrase.is_none() && self.multisig.is_none()
+    }
+
+    pub fn is_multisig(&self) -> bool {
+        self.multisig.is_some()
+    }
+
+    /// Returns true if both records hold the same keys, regardless of name and creation time.
+    pub fn same_keys(&self, other: &WalletRecord) -> bool {
+        self.seedphrase.as_ref().map(|s| s.expose())
+            == other.seedphrase.as_ref().map(|s| s.expose())
+            && self.master_pubkey == other.master_pubkey
+            && self.multisig == other.multisig
+    }
+}
+
+/// A key derived from the vault password, together with the salt and parameters
+/// it was derived with. Writes reuse it with a fresh nonce.
+#[derive(Clone)]
+pub struct VaultKey {
+    key: Secret<[u8; 32]>,
+    salt: [u8; SALT_LEN],
+    kdf: KdfParams,
+}
+
+impl fmt::Debug for VaultKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VaultKey")
+            .field("kdf", &self.kdf)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VaultKey {
+    /// Derives a new key from `password` under a freshly generated salt.
+    pub fn derive(password: &str, kdf: KdfParams) -> Result<Self, String> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng
+            .try_fill_bytes(&mut salt)
+            .map_err(|e| format!("Failed to fill salt: {e}"))?;
+        let key = derive_key(password, &salt, &kdf)?;
+        Ok(Self { key, salt, kdf })
+    }
+
+    pub fn kdf(&self) -> KdfParams {
+        self.kdf
+    }
+
+    /// Returns true if `password` derives this same key.
+    pub fn matches(&self, password: &str) -> Result<bool, String> {
+        let candidate = derive_key(password, &self.salt, &self.kdf)?;
+        // constant-time comparison
+        let diff = candidate
+            .expose()
+            .iter()
+            .zip(self.key.expose().iter())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        Ok(diff == 0)
+    }
+}
+
+/// The kinds of files written in this format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    Vault,
+    Export,
+}
+
+impl FileKind {
+    fn magic(self) -> &'static [u8] {
+        match self {
+            FileKind::Vault => HEADER_MAGIC,
+            FileKind::Export => EXPORT_MAGIC,
+        }
+    }
+}
+
+/// A vault file split into its parts, before decryption.
+pub struct Envelope {
+    pub version: u8,
+    kdf: KdfParams,
+    header: Vec<u8>,
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+    ciphertext: Vec<u8>,
+}
+
+impl Envelope {
+    /// Decodes the Base64 file contents and splits out header, salt, nonce and ciphertext.
+    pub fn parse(b64_payload: &str, kind: FileKind) -> Result<Self, String> {
+        let payload = general_purpose::STANDARD
+            .decode(b64_payload.trim())
+            .map_err(|e| format!("Base64 decoding failed: {}", e))?;
+        let mut cursor = Cursor::new(&payload);
+
+        // 1) Identify the format from the magic
+        let mut magic = [0u8; 8];
+        cursor
+            .read_exact(&mut magic)
+            .map_err(|_| "File too short or corrupted (missing header)".to_string())?;
+        let version = if kind == FileKind::Vault && magic == LEGACY_MAGIC {
+            LEGACY_VERSION
+        } else if magic == kind.magic() {
+            let mut version = [0u8; 1];
+            cursor
+                .read_exact(&mut version)
+                .map_err(|_| "File too short or corrupted (missing version)".to_string())?;
+            if version[0] <= LEGACY_VERSION || version[0] > FORMAT_VERSION {
+                return Err(format!("Unsupported vault version: {}", version[0]));
+            }
+            version[0]
+        } else {
+            return Err("Invalid file format".to_string());
+        };
+
+        // 1b) Read the Argon2 parameters, older versions used the defaults
+        let kdf = if version >= KDF_HEADER_VERSION {
+            let mut kdf_bytes = [0u8; 12];
+            cursor
+                .read_exact(&mut kdf_bytes)
+                .map_err(|_| "File too short or corrupted (missing KDF params)".to_string())?;
+            let kdf = KdfParams::from_bytes(kdf_bytes);
+            kdf.validate()
+                .map_err(|e| format!("File corrupted (invalid KDF params): {}", e))?;
+            kdf
+        } else {
+            KdfParams::default()
+        };
+        let header = payload[..cursor.position() as usize].to_vec();
+
+        // 2) Extract salt
+        let mut salt = [0u8; SALT_LEN];
+        cursor
+            .read_exact(&mut salt)
+            .map_err(|_| "File too short or corrupted (missing salt)".to_string())?;
+
+        // 3) Extract nonce
+        let mut nonce = [0u8; NONCE_LEN];
+        cursor
+            .read_exact(&mut nonce)
+            .map_err(|_| "File too short or corrupted (missing nonce)".to_string())?;
+
+        // 4) The remainder is ciphertext (+ 16-byte auth tag)
+        let mut ciphertext = Vec::new();
+        cursor
+            .read_to_end(&mut ciphertext)
+            .map_err(|e| format!("Failed to read ciphertext bytes: {}", e))?;
+
+        Ok(Self {
+            version,
+            kdf,
+            header,
+            salt,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// Derives the key for this file from `password`, using the stored salt and parameters.
+    pub fn derive_key(&self, password: &str) -> Result<VaultKey, String> {
+        let key = derive_key(password, &self.salt, &self.kdf)?;
+        Ok(VaultKey {
+            key,
+            salt: self.salt,
+            kdf: self.kdf,
+        })
+    }
+
+    /// Decrypts the envelope with `key` and parses the plaintext for its version.
+    pub fn decrypt(&self, key: &VaultKey) -> Result<VaultPayload, String> {
+        let plaintext_bytes = self.open(key)?;
+        match self.version {
+            LEGACY_VERSION => parse_legacy(plaintext_bytes),
+            _ => serde_json::from_slice(plaintext_bytes.expose())
+                .map_err(|e| format!("Decrypted data is not a valid vault payload: {}", e)),
+        }
+    }
+
+    /// Decrypts and authenticates the ciphertext, returning the raw plaintext.
+    fn open(&self, key: &VaultKey) -> Result<Secret<Vec<u8>>, String> {
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(key.key.expose()));
+        let nonce = XNonce::from_slice(&self.nonce);
+
+        // v1 did not authenticate its header
+        let plaintext_bytes = if self.version == LEGACY_VERSION {
+            cipher.decrypt(nonce, self.ciphertext.as_ref())
+        } else {
+            cipher.decrypt(
+                nonce,
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: &self.header,
+                },
+            )
+        }
+        .map_err(|_| "Decryption failed: wrong password or corrupted file".to_string())?;
+        Ok(Secret::new(plaintext_bytes))
+    }
+}
+
+/// Serializes and encrypts `payload` with `key` under a fresh nonce, returning the
+/// Base64 file contents of the given kind in the current format.
+pub fn encrypt(payload: &VaultPayload, key: &VaultKey, kind: FileKind) -> Result<String, String> {
+    // 1) Serialize the payload
+    let plaintext_bytes = Secret::new(
+        serde_json::to_vec(payload)
+            .map_err(|e| format!("Failed to serialize vault payload: {}", e))?,
+    );
+
+    // 2) Initialize XChaCha20‐Poly1305 and generate a random nonce
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key.key.expose()));
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    OsRng
+        .try_fill_bytes(&mut nonce_bytes)
+        .map_err(|e| format!("Failed to fill nonce: {e}"))?;
+    let nonce = XNonce::from_slice(&nonce_bytes);
+
+    // 3) Encrypt + authenticate, binding the header to the ciphertext
+    let mut header = Vec::new();
+    header.extend_from_slice(kind.magic());
+    header.push(FORMAT_VERSION);
+    header.extend_from_slice(&key.kdf.to_bytes());
+    let ciphertext = cipher
+        .encrypt(
+            nonce,
+            Payload {
+                msg: plaintext_bytes.expose(),
+                aad: &header,
+            },
+        )
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+
+    // 4) Build the payload: header ∥ salt ∥ nonce ∥ ciphertext
+    let mut payload = header;
+    payload.extend_from_slice(&key.salt);
+    payload.extend_from_slice(&nonce_bytes);
+    payload.extend_from_slice(&ciphertext);
+
+    Ok(general_purpose::STANDARD.encode(&payload))
+}
+
+/// Derives a 32‐byte key via Argon2id.
+fn derive_key(password: &str, salt: &[u8], kdf: &KdfParams) -> Result<Secret<[u8; 32]>, String> {
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, None)
+        .map_err(|e| format!("Invalid Argon2 params: {}", e))?;
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+    let mut key_bytes = Secret::new([0u8; 32]);
+    argon2
+        .hash_password_into(password.as_bytes(), salt, key_bytes.expose_mut())
+        .map_err(|e| format!("Argon2 key derivation failed: {}", e))?;
+    Ok(key_bytes)
+}
+
+/// Parses the v1 plaintext: one `name\tseedphrase` per line, tabs and newlines escaped.
+fn parse_legacy(plaintext_bytes: Secret<Vec<u8>>) -> Result<VaultPayload, String> {
+    let plaintext_str = std::str::from_utf8(plaintext_bytes.expose())
+        .map_err(|e| format!("Decrypted data is not valid UTF-8: {}", e))?;
+    let mut wallets = HashMap::new();
+    for line in plaintext_str.lines() {
+        if let Some((k, v)) = line.split_once('\t') {
+            // Unescape tabs and newlines
+            let k = k.replace("\\t", "\t").replace("\\n", "\n");
+            let v = v.replace("\\t", "\t").replace("\\n", "\n");
+            wallets.insert(
+                k,
+                WalletRecord {
+                    seedphrase: Some(SecretString::new(v)),
+                    master_pubkey: None,
+                    multisig: None,
+                    created_at: None,
+                },
+            );
+        }
+    }
+    Ok(VaultPayload { wallets })
+}
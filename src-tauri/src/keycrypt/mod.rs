@@ -0,0 +1,419 @@
+// keycrypt/mod.rs
+
+mod export;
+mod format;
+mod storage;
+mod throttle;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use format::{Envelope, FileKind, VaultKey, VaultPayload, WalletRecord};
+use throttle::Throttle;
+
+use crate::pubkey::Lock;
+use crate::secret::SecretString;
+
+pub use export::{ImportConflict, ImportReport};
+pub use format::KdfParams;
+
+/// Checks that `wallet_name` can name a wallet. The name is also the directory of the
+/// wallet's drafts and history, so it can't be empty, `.` or `..`, or contain a path
+/// separator or NUL.
+pub fn validate_wallet_name(wallet_name: &str) -> Result<(), String> {
+    if wallet_name.trim().is_empty() {
+        return Err("Wallet name cannot be empty".to_string());
+    }
+    if wallet_name == "." || wallet_name == ".." {
+        return Err(format!("{} is not a valid wallet name", wallet_name));
+    }
+    if wallet_name.contains(['/', '\\', '\0']) {
+        return Err("Wallet name cannot contain /, \\ or NUL".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct Keycrypt {
+    loaded: bool,
+    data: HashMap<String, WalletRecord>,
+    key: Option<VaultKey>,
+    enc: PathBuf,
+    throttle: Throttle,
+}
+
+impl Keycrypt {
+    /// Creates a new `Keycrypt` instance with:
+    /// - `enc_path`: PathBuf to the encrypted file
+    /// The `data` vector starts empty, and no key is held until `create` or `load`.
+    pub fn new(enc: PathBuf) -> Self {
+        Keycrypt {
+            loaded: false,
+            data: HashMap::new(),
+            key: None,
+            throttle: Throttle::load(&enc),
+            enc,
+        }
+    }
+
+    /// Returns true if the encrypted file, or a backup of it, exists.
+    pub fn vault_exists(&self) -> bool {
+        (self.enc.exists() && self.enc.is_file()) || self.backup_count() > 0
+    }
+
+    /// Returns the number of previous vault generations kept on disk.
+    pub fn backup_count(&self) -> usize {
+        storage::backups(&self.enc).len()
+    }
+
+    /// Returns true if the encrypted file has been loaded and decrypted.
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    /// Seconds until another password attempt is accepted after repeated failures.
+    pub fn unlock_retry_in(&self) -> Option<u64> {
+        self.throttle.retry_in()
+    }
+
+    /// Creates an empty vault protected by `password`. `kdf` overrides the default
+    /// Argon2 parameters, e.g. with the result of `KdfParams::calibrate`.
+    pub fn create(&mut self, password: SecretString, kdf: Option<KdfParams>) -> Result<(), String> {
+        let kdf = kdf.unwrap_or_default();
+        kdf.validate()?;
+        self.key = Some(VaultKey::derive(password.expose(), kdf)?);
+        self.write()?;
+        self.loaded = true;
+        tracing::debug!("create: vault created at {:?}", self.enc);
+        Ok(())
+    }
+
+    /// Loads the encrypted file and decrypts it using the provided password.
+    /// The derived key is kept for subsequent writes.
+    pub fn load(&mut self, password: SecretString) -> Result<(), String> {
+        self.decrypt(password.expose())?;
+        self.loaded = true;
+        tracing::debug!("load: {} wallets", self.data.len());
+        Ok(())
+    }
+
+    /// Restores the newest backup that decrypts with `password`, for when the vault
+    /// file itself is corrupted. A vault that is loaded or that opens with `password`
+    /// is left alone, so a healthy vault is never rolled back to an older generation.
+    /// The unreadable file is kept aside as `vault.corrupt-<ts>`.
+    /// Returns the generation that was restored (1 is the newest).
+    pub fn recover(&mut self, password: SecretString) -> Result<usize, String> {
+        if self.loaded {
+            return Err("Vault is loaded, there is nothing to recover".to_string());
+        }
+        self.throttle.check()?;
+        if self.enc.exists() {
+            match Self::open(&self.enc, password.expose()) {
+                Ok(_) => {
+                    return Err(
+                        "Vault opens with this password, there is nothing to recover".to_string(),
+                    )
+                }
+                Err(e) => tracing::warn!("recover: vault {:?} failed: {}", self.enc, e),
+            }
+        }
+        for (i, backup) in storage::backups(&self.enc).iter().enumerate() {
+            let (key, payload, _) = match Self::open(backup, password.expose()) {
+                Ok(opened) => opened,
+                Err(e) => {
+                    tracing::warn!("recover: backup {:?} failed: {}", backup, e);
+                    continue;
+                }
+            };
+            if self.enc.exists() {
+                let corrupt = storage::quarantine(&self.enc)
+                    .map_err(|e| format!("Failed to move corrupted vault aside: {}", e))?;
+                tracing::warn!("recover: moved unreadable vault to {:?}", corrupt);
+            }
+            self.throttle.record_success();
+            self.data = payload.wallets;
+            self.key = Some(key);
+            self.write()?;
+            self.loaded = true;
+            tracing::info!("recover: restored vault from {:?}", backup);
+            return Ok(i + 1);
+        }
+        // one failure per call, not per backup tried
+        self.throttle.record_failure();
+        Err("No backup could be decrypted with this password".to_string())
+    }
+
+    /// Re-encrypts the vault under `new_password` after verifying `old_password`
+    /// against the cached key. The new key is derived under a fresh salt.
+    /// `kdf` replaces the Argon2 parameters, the current ones are kept if `None`.
+    /// The backups, encrypted under the old password, are replaced by one under the new.
+    pub fn change_password(
+        &mut self,
+        old_password: SecretString,
+        new_password: SecretString,
+        kdf: Option<KdfParams>,
+    ) -> Result<(), String> {
+        let Some(key) = &self.key else {
+            return Err("Vault not loaded".to_string());
+        };
+        let kdf = kdf.unwrap_or(key.kdf());
+        kdf.validate()?;
+        self.verify_password(old_password.expose())?;
+
+        let new_key = VaultKey::derive(new_password.expose(), kdf)?;
+        let old_key = self.key.replace(new_key);
+        if let Err(e) = self.write() {
+            self.key = old_key;
+            return Err(e);
+        }
+        tracing::info!("vault password changed");
+        self.discard_backups()
+    }
+
+    /// Drops the decrypted wallets and the cached key. `load` is needed to use the vault again.
+    pub fn lock(&mut self) {
+        self.data.clear();
+        self.key = None;
+        self.loaded = false;
+    }
+
+    /// Returns a list of all wallet names.
+    pub fn get_wallets(&self) -> Vec<String> {
+        self.data.keys().cloned().collect()
+    }
+
+    /// Returns the names of the wallets that only hold a master pubkey.
+    pub fn get_watch_only_wallets(&self) -> Vec<String> {
+        self.data
+            .iter()
+            .filter(|(_, record)| record.is_watch_only())
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    pub fn get_multisig_wallets(&self) -> Vec<String> {
+        self.data
+            .iter()
+            .filter(|(_, record)| record.is_multisig())
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    pub fn get_seedphrase(&self, wallet_name: String) -> Result<SecretString, String> {
+        let record = self.get_record(&wallet_name)?;
+        match &record.seedphrase {
+            Some(seedphrase) => Ok(seedphrase.clone()),
+            None if record.is_multisig() => Err(format!("Wallet {} is multisig", wallet_name)),
+            None => Err(format!("Wallet {} is watch-only", wallet_name)),
+        }
+    }
+
+    /// Returns the stored master pubkey if `wallet_name` is a watch-only wallet, `None` otherwise.
+    pub fn get_watch_only_pubkey(&self, wallet_name: String) -> Result<Option<String>, String> {
+        let record = self.get_record(&wallet_name)?;
+        if !record.is_watch_only() {
+            return Ok(None);
+        }
+        Ok(record.master_pubkey.clone())
+    }
+
+    /// Returns the key set of `wallet_name` if it is a multisig wallet, `None` otherwise.
+    pub fn get_multisig_lock(&self, wallet_name: String) -> Result<Option<Lock>, String> {
+        let record = self.get_record(&wallet_name)?;
+        Ok(record.multisig.clone())
+    }
+
+    pub fn add_wallet(
+        &mut self,
+        wallet_name: String,
+        seedphrase: SecretString,
+    ) -> Result<(), String> {
+        if !self.loaded {
+            return Err("Vault not loaded".to_string());
+        }
+        validate_wallet_name(&wallet_name)?;
+        if self.data.contains_key(&wallet_name) {
+            return Err(format!("Wallet {} already exists", wallet_name));
+        }
+        self.data.insert(wallet_name, WalletRecord::new(seedphrase));
+        self.write()?;
+        tracing::debug!("add_wallet: {} wallets", self.data.len());
+        Ok(())
+    }
+
+    /// Adds a wallet that can be monitored but not spent from, stored by its master pubkey.
+    pub fn add_watch_only(
+        &mut self,
+        wallet_name: String,
+        master_pubkey: String,
+    ) -> Result<(), String> {
+        if !self.loaded {
+            return Err("Vault not loaded".to_string());
+        }
+        validate_wallet_name(&wallet_name)?;
+        if self.data.contains_key(&wallet_name) {
+            return Err(format!("Wallet {} already exists", wallet_name));
+        }
+        self.data
+            .insert(wallet_name, WalletRecord::watch_only(master_pubkey));
+        self.write()?;
+        tracing::debug!("add_watch_only: {} wallets", self.data.len());
+        Ok(())
+    }
+
+    /// Adds a wallet that tracks the notes locked to a set of keys, spent with the
+    /// signatures of the wallets holding them.
+    pub fn add_multisig(&mut self, wallet_name: String, lock: Lock) -> Result<(), String> {
+        if !self.loaded {
+            return Err("Vault not loaded".to_string());
+        }
+        validate_wallet_name(&wallet_name)?;
+        if self.data.contains_key(&wallet_name) {
+            return Err(format!("Wallet {} already exists", wallet_name));
+        }
+        self.data.insert(wallet_name, WalletRecord::multisig(lock));
+        self.write()?;
+        tracing::debug!("add_multisig: {} wallets", self.data.len());
+        Ok(())
+    }
+
+    /// Moves the wallet stored under `old_name` to `new_name`.
+    pub fn rename_wallet(&mut self, old_name: String, new_name: String) -> Result<(), String> {
+        if !self.loaded {
+            return Err("Vault not loaded".to_string());
+        }
+        validate_wallet_name(&new_name)?;
+        if self.data.contains_key(&new_name) {
+            return Err(format!("Wallet {} already exists", new_name));
+        }
+        let Some(record) = self.data.remove(&old_name) else {
+            return Err(format!("Wallet {} not found", old_name));
+        };
+        self.data.insert(new_name.clone(), record);
+        if let Err(e) = self.write() {
+            let record = self.data.remove(&new_name).unwrap();
+            self.data.insert(old_name, record);
+            return Err(e);
+        }
+        tracing::debug!("rename_wallet: renamed a wallet");
+        Ok(())
+    }
+
+    /// Deletes `wallet_name` from the vault after checking `password` against the cached key.
+    /// The backups, which still hold the wallet, are replaced by one without it.
+    pub fn remove_wallet(
+        &mut self,
+        wallet_name: String,
+        password: SecretString,
+    ) -> Result<(), String> {
+        self.verify_password(password.expose())?;
+        let Some(record) = self.data.remove(&wallet_name) else {
+            return Err(format!("Wallet {} not found", wallet_name));
+        };
+        if let Err(e) = self.write() {
+            self.data.insert(wallet_name, record);
+            return Err(e);
+        }
+        tracing::debug!("remove_wallet: {} wallets", self.data.len());
+        self.discard_backups()
+    }
+
+    /// Checks `password` against the cached key, counting failures towards the unlock backoff.
+    pub fn verify_password(&mut self, password: &str) -> Result<(), String> {
+        let Some(key) = &self.key else {
+            return Err("Vault not loaded".to_string());
+        };
+        self.throttle.check()?;
+        if !key.matches(password)? {
+            self.throttle.record_failure();
+            return Err("Wrong password".to_string());
+        }
+        self.throttle.record_success();
+        Ok(())
+    }
+
+    // drops the backups of earlier generations, once the vault was written without
+    // something they must not keep
+    fn discard_backups(&self) -> Result<(), String> {
+        storage::reset_backups(&self.enc)
+            .map_err(|e| format!("Failed to remove old vault backups: {}", e))
+    }
+
+    fn get_record(&self, wallet_name: &str) -> Result<&WalletRecord, String> {
+        if !self.loaded {
+            return Err("Vault not loaded".to_string());
+        }
+        self.data
+            .get(wallet_name)
+            .ok_or_else(|| format!("Wallet {} not found", wallet_name))
+    }
+
+    /// Encrypts `self.data` using the cached key in the current vault format
+    /// and writes the Base64‐encoded file to `self.enc`.
+    fn write(&self) -> Result<(), String> {
+        let Some(key) = &self.key else {
+            return Err("Vault not loaded".to_string());
+        };
+        let payload = VaultPayload {
+            wallets: self.data.clone(),
+        };
+        let b64_payload = format::encrypt(&payload, key, FileKind::Vault)?;
+        storage::write_with_backup(&self.enc, b64_payload.as_bytes())
+            .map_err(|e| format!("Failed to write encrypted file: {}", e))?;
+        Ok(())
+    }
+
+    /// Reads the encrypted file from `self.enc`, derives its key from `password`,
+    /// and loads the wallets into `self.data`. Files in an older format are
+    /// rewritten in the current one.
+    fn decrypt(&mut self, password: &str) -> Result<(), String> {
+        self.throttle.check()?;
+        let (key, payload, version) = match Self::open(&self.enc, password) {
+            Ok(opened) => opened,
+            Err(OpenError::Password(e)) => {
+                self.throttle.record_failure();
+                return Err(e);
+            }
+            Err(OpenError::File(e)) => return Err(e),
+        };
+        self.throttle.record_success();
+        self.data = payload.wallets;
+        self.key = Some(key);
+
+        if version < format::FORMAT_VERSION {
+            tracing::info!(
+                "migrating vault from v{} to v{}",
+                version,
+                format::FORMAT_VERSION
+            );
+            self.write()?;
+        }
+        Ok(())
+    }
+
+    /// Reads and decrypts the vault file at `path`, returning its key, contents and format version.
+    fn open(path: &Path, password: &str) -> Result<(VaultKey, VaultPayload, u8), OpenError> {
+        let b64_payload = fs::read_to_string(path)
+            .map_err(|e| OpenError::File(format!("Failed to read encrypted file: {}", e)))?;
+        let envelope = Envelope::parse(&b64_payload, FileKind::Vault).map_err(OpenError::File)?;
+        let key = envelope.derive_key(password).map_err(OpenError::File)?;
+        let payload = envelope.decrypt(&key).map_err(OpenError::Password)?;
+        Ok((key, payload, envelope.version))
+    }
+}
+
+/// Separates failures that count as a wrong password from unreadable or malformed files.
+enum OpenError {
+    File(String),
+    Password(String),
+}
+
+impl fmt::Display for OpenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenError::File(e) | OpenError::Password(e) => f.write_str(e),
+        }
+    }
+}
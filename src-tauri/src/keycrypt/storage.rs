@@ -0,0 +1,93 @@
+// keycrypt/storage.rs
+//
+// File handling for the vault: atomic replacement of the vault file and the
+// rotating backups kept next to it (`vault.1` is the newest, `vault.N` the oldest).
+// Backups are reset when older generations must not stay readable, after a
+// password change or the removal of a wallet.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::timestamp::now_secs;
+
+/// Number of previous vault generations kept as backups.
+pub const BACKUP_GENERATIONS: usize = 5;
+
+/// Replaces `path` with `contents`, moving the current file into the backup rotation first.
+pub fn write_with_backup(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if path.is_file() {
+        rotate_backups(path)?;
+    }
+    write_atomic(path, contents)
+}
+
+/// Returns the existing backups of `path`, newest first.
+pub fn backups(path: &Path) -> Vec<PathBuf> {
+    (1..=BACKUP_GENERATIONS)
+        .map(|generation| backup_path(path, generation))
+        .filter(|p| p.is_file())
+        .collect()
+}
+
+/// Deletes all backups of `path` and starts the rotation again with a copy of the
+/// current file. The old files are unlinked, not overwritten, so their contents may
+/// remain on the disk until the space is reused.
+pub fn reset_backups(path: &Path) -> std::io::Result<()> {
+    for backup in backups(path) {
+        fs::remove_file(backup)?;
+    }
+    if path.is_file() {
+        fs::copy(path, backup_path(path, 1))?;
+    }
+    Ok(())
+}
+
+/// Moves a vault file that failed to load out of the way, keeping it for inspection.
+pub fn quarantine(path: &Path) -> std::io::Result<PathBuf> {
+    let corrupt = sibling(path, &format!("corrupt-{}", now_secs()));
+    fs::rename(path, &corrupt)?;
+    Ok(corrupt)
+}
+
+/// Shifts `vault.1..vault.N-1` up by one generation, dropping the oldest,
+/// and copies the current file to `vault.1`.
+fn rotate_backups(path: &Path) -> std::io::Result<()> {
+    for generation in (1..BACKUP_GENERATIONS).rev() {
+        let from = backup_path(path, generation);
+        if from.is_file() {
+            fs::rename(&from, backup_path(path, generation + 1))?;
+        }
+    }
+    fs::copy(path, backup_path(path, 1))?;
+    Ok(())
+}
+
+/// Writes `contents` to a temporary file next to `path`, syncs it and renames it
+/// over `path`, so a crash mid-write leaves either the old or the new file intact.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let tmp = sibling(path, "tmp");
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+    // persist the rename itself
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+fn backup_path(path: &Path, generation: usize) -> PathBuf {
+    sibling(path, &generation.to_string())
+}
+
+// `vault` -> `vault.<suffix>`
+pub fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
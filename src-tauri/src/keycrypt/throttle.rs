@@ -0,0 +1,103 @@
+// keycrypt/throttle.rs
+//
+// Backoff for failed unlock attempts. The count is kept in `vault.attempts` next to
+// the vault, so restarting the app doesn't reset it. After `FREE_ATTEMPTS` failures
+// every further attempt has to wait twice as long as the previous one, up to an hour.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::storage;
+use crate::timestamp::now_secs;
+
+/// Failed attempts allowed before the backoff starts.
+const FREE_ATTEMPTS: u32 = 3;
+const BASE_DELAY_SECS: u64 = 2;
+const MAX_DELAY_SECS: u64 = 60 * 60;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Attempts {
+    failures: u32,
+    /// Unix timestamp in seconds
+    last_failure: u64,
+}
+
+#[derive(Debug)]
+pub struct Throttle {
+    path: PathBuf,
+    attempts: Attempts,
+}
+
+impl Throttle {
+    /// Reads the attempt count stored beside `vault_path`, starting from zero if there is none.
+    pub fn load(vault_path: &Path) -> Self {
+        let path = storage::sibling(vault_path, "attempts");
+        let attempts = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("throttle: unreadable attempts file {:?}: {}", path, e);
+                // don't let a damaged file lift the backoff
+                Attempts {
+                    failures: FREE_ATTEMPTS,
+                    last_failure: now_secs(),
+                }
+            }),
+            Err(_) => Attempts::default(),
+        };
+        Self { path, attempts }
+    }
+
+    /// Seconds until the next attempt is allowed, `None` if it is allowed now.
+    pub fn retry_in(&self) -> Option<u64> {
+        let delay = delay_secs(self.attempts.failures);
+        let elapsed = now_secs().saturating_sub(self.attempts.last_failure);
+        (elapsed < delay).then(|| delay - elapsed)
+    }
+
+    /// Returns an error if the backoff from previous failures hasn't passed yet.
+    pub fn check(&self) -> Result<(), String> {
+        match self.retry_in() {
+            Some(secs) => Err(format!(
+                "Too many failed attempts, try again in {} seconds",
+                secs
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn record_failure(&mut self) {
+        self.attempts.failures = self.attempts.failures.saturating_add(1);
+        self.attempts.last_failure = now_secs();
+        tracing::warn!("throttle: {} failed attempts", self.attempts.failures);
+        self.persist();
+    }
+
+    pub fn record_success(&mut self) {
+        if self.attempts.failures == 0 {
+            return;
+        }
+        self.attempts = Attempts::default();
+        if let Err(e) = fs::remove_file(&self.path) {
+            tracing::error!("throttle: failed to remove {:?}: {}", self.path, e);
+        }
+    }
+
+    fn persist(&self) {
+        let content = serde_json::to_string(&self.attempts).unwrap();
+        if let Err(e) = storage::write_atomic(&self.path, content.as_bytes()) {
+            tracing::error!("throttle: failed to write {:?}: {}", self.path, e);
+        }
+    }
+}
+
+fn delay_secs(failures: u32) -> u64 {
+    if failures < FREE_ATTEMPTS {
+        return 0;
+    }
+    let exponent = (failures - FREE_ATTEMPTS).min(32);
+    BASE_DELAY_SECS
+        .saturating_mul(1 << exponent)
+        .min(MAX_DELAY_SECS)
+}
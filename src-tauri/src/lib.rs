@@ -1,8 +1,15 @@
+mod amount;
 mod commands;
+mod decode;
 mod keycrypt;
 mod manager;
 mod prover;
+mod pubkey;
+mod secret;
+mod seedphrase;
 mod services;
+mod shamir;
+mod timestamp;
 mod update_checker;
 mod wallet_app;
 mod watcher;
@@ -15,6 +22,7 @@ use crate::commands::*;
 use crate::keycrypt::Keycrypt;
 use crate::watcher::Watcher;
 
+use crate::commands::lock::AutoLockState;
 use crate::commands::terms::TermsState;
 use std::time::Duration;
 use tracing::error;
@@ -41,6 +49,7 @@ pub async fn run() {
             let data_dir: std::path::PathBuf = app.path().app_data_dir().unwrap();
             let wallet_dir = data_dir.join("wallet");
             let draft_dir = data_dir.join("draft");
+            let history_dir = data_dir.join("history");
             let nockchain_dir = data_dir.join("nockchain");
             let keycrypt_dir = data_dir.join("vault");
 
@@ -72,10 +81,12 @@ pub async fn run() {
 
             // --- Application State Management ---
             app.manage(Mutex::new(TermsState::new(&app.handle())));
+            app.manage(Mutex::new(AutoLockState::new(&app.handle())));
             app.manage(Mutex::new(manager::Wallet::new(
                 wallet_tx,
                 wallet_dir.clone(),
                 draft_dir.clone(),
+                history_dir.clone(),
             )));
             app.manage(Mutex::new(manager::NockchainNode::new(nockchain_tx)));
             app.manage(Mutex::new(Keycrypt::new(keycrypt_dir)));
@@ -150,6 +161,27 @@ pub async fn run() {
                 }
             });
 
+            // --- Auto-Lock Service ---
+            // locks the vault and unloads the wallet after the configured idle time
+            let auto_lock_app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let auto_lock = auto_lock_app_handle.state::<Mutex<AutoLockState>>();
+                let vault = auto_lock_app_handle.state::<Mutex<Keycrypt>>();
+                let mut interval = tokio::time::interval(Duration::from_secs(5));
+                loop {
+                    interval.tick().await;
+                    if !auto_lock.lock().await.is_idle() {
+                        continue;
+                    }
+                    if !vault.lock().await.is_loaded() {
+                        continue;
+                    }
+                    if let Err(e) = lock::lock_session(&auto_lock_app_handle).await {
+                        error!("Failed to auto-lock: {}", e);
+                    }
+                }
+            });
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -165,15 +197,42 @@ pub async fn run() {
             // wallet
             wallet::vault_create,
             wallet::vault_load,
+            wallet::vault_recover,
+            wallet::vault_change_password,
+            wallet::vault_calibrate_kdf,
+            wallet::vault_export,
+            wallet::vault_import,
             wallet::wallet_create,
+            wallet::wallet_create_watch_only,
+            wallet::wallet_create_multisig,
+            wallet::seedphrase_validate,
             wallet::keygen,
             wallet::wallet_load,
+            wallet::wallet_rename,
+            wallet::wallet_remove,
             wallet::master_pubkey,
             wallet::balance,
+            wallet::list_notes,
+            wallet::estimate_fee,
             wallet::create_tx,
+            wallet::inspect_draft,
             wallet::sign_tx,
+            wallet::cosign_tx,
             wallet::send_tx,
+            wallet::cancel_tx,
+            wallet::export_draft,
+            wallet::import_draft,
+            wallet::import_signed,
             wallet::list_unsent_txs,
+            wallet::tx_history,
+            // lock
+            lock::vault_lock,
+            lock::session_touch,
+            lock::auto_lock_timeout,
+            lock::set_auto_lock_timeout,
+            // backup
+            backup::backup_split,
+            backup::backup_recover,
             // nockchain node
             nockchain_node::node_start_master,
             nockchain_node::node_stop_master,
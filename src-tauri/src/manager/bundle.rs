@@ -0,0 +1,292 @@
+// manager/bundle.rs
+//
+// Draft bundles, single files for moving a draft between Aeroe installs, e.g. to
+// sign it on an offline machine that holds the seed. A bundle carries the draft's
+// metadata, the `.draft` file written by the kernel and, once signed, the `.signed`
+// file, each with its SHA-256 digest. A draft is only imported if its file decodes
+// to the transaction its metadata describes. A signed file is only accepted back if
+// the bundle was made from the exact draft file this install holds and the signed
+// file decodes to that same transaction, signed.
+
+use std::fs;
+use std::path::Path;
+
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::draft;
+use super::inspect::{self, DraftContents};
+use super::wallet::{NockchainTxMeta, NockchainTxStatus};
+use crate::pubkey::Lock;
+use crate::timestamp::now_nanos;
+
+const BUNDLE_VERSION: u32 = 1;
+const MAX_DRAFT_ID_LEN: usize = 64;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleFile {
+    version: u32,
+    meta: NockchainTxMeta,
+    /// base64 of the `.draft` file
+    draft: String,
+    /// hex SHA-256 of the `.draft` file
+    draft_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signed: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signed_hash: Option<String>,
+}
+
+/// The verified contents of a bundle.
+pub struct Bundle {
+    pub meta: NockchainTxMeta,
+    pub draft: Vec<u8>,
+    pub signed: Option<Vec<u8>>,
+}
+
+/// Writes the draft `meta` stored in `dir` to a bundle at `path`, with its signed
+/// file if it has one.
+pub fn write(dir: &Path, meta: &NockchainTxMeta, path: &Path) -> Result<(), String> {
+    let draft = fs::read(draft::draft_path(dir, &meta.draft_id))
+        .map_err(|e| format!("failed to read draft file: {}", e))?;
+    let signed_path = draft::signed_path(dir, &meta.draft_id);
+    let signed = if signed_path.exists() {
+        Some(fs::read(&signed_path).map_err(|e| format!("failed to read signed file: {}", e))?)
+    } else {
+        None
+    };
+    let bundle = BundleFile {
+        version: BUNDLE_VERSION,
+        meta: meta.clone(),
+        draft_hash: digest(&draft),
+        draft: general_purpose::STANDARD.encode(&draft),
+        signed_hash: signed.as_deref().map(digest),
+        signed: signed.map(|signed| general_purpose::STANDARD.encode(signed)),
+    };
+    let content = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| format!("failed to write bundle: {}", e))
+}
+
+/// Reads the bundle at `path`, checking its files against their digests.
+pub fn read(path: &Path) -> Result<Bundle, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("failed to read bundle: {}", e))?;
+    let bundle: BundleFile =
+        serde_json::from_str(&content).map_err(|e| format!("invalid bundle: {}", e))?;
+    if bundle.version != BUNDLE_VERSION {
+        return Err(format!("unsupported bundle version {}", bundle.version));
+    }
+    check_draft_id(&bundle.meta.draft_id)?;
+    let draft = decode("draft", &bundle.draft, &bundle.draft_hash)?;
+    let signed = match (&bundle.signed, &bundle.signed_hash) {
+        (Some(signed), Some(hash)) => Some(decode("signed", signed, hash)?),
+        (None, None) => None,
+        _ => return Err("bundle has a signed file without its digest".to_string()),
+    };
+    Ok(Bundle {
+        meta: bundle.meta,
+        draft,
+        signed,
+    })
+}
+
+/// Stores the unsigned draft of `bundle` in `dir`, returning its metadata as a new draft,
+/// after checking the draft file decodes to the transaction the metadata describes.
+/// The signed file of a multisig draft still collecting signatures comes along, so
+/// the next cosigner signs over the signatures made so far.
+pub fn import_draft(dir: &Path, bundle: Bundle) -> Result<NockchainTxMeta, String> {
+    let draft_path = draft::draft_path(dir, &bundle.meta.draft_id);
+    if draft_path.exists() {
+        return Err(format!("draft {} already exists", bundle.meta.draft_id));
+    }
+    let contents =
+        inspect::decode(bundle.draft.clone()).map_err(|e| format!("bundle's draft file: {}", e))?;
+    inspect::check(&bundle.meta, &contents)
+        .map_err(|e| format!("bundle's draft file does not match its details: {}", e))?;
+    let cosigned = match (&bundle.signed, &bundle.meta.status) {
+        (Some(signed), NockchainTxStatus::Cosigning) => {
+            let signed_contents = inspect::decode(signed.clone())
+                .map_err(|e| format!("bundle's signed file: {}", e))?;
+            let signed_by = inspect::check_signed_over(&contents, &signed_contents)
+                .map_err(|e| format!("bundle's {}", e))?;
+            Some((signed, signed_by))
+        }
+        _ => None,
+    };
+    let mut meta = NockchainTxMeta {
+        status: NockchainTxStatus::Draft,
+        signed_at: None,
+        broadcasted_at: None,
+        broadcast_height: None,
+        confirmed_height: None,
+        signed_by: Vec::new(),
+        inputs_missing_at: None,
+        ..bundle.meta.clone()
+    };
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    fs::write(&draft_path, &bundle.draft)
+        .map_err(|e| format!("failed to write draft file: {}", e))?;
+    if let Some((signed, signed_by)) = cosigned {
+        fs::write(draft::signed_path(dir, &meta.draft_id), signed)
+            .map_err(|e| format!("failed to write signed file: {}", e))?;
+        meta.status = NockchainTxStatus::Cosigning;
+        meta.signed_by = signed_by;
+    }
+    draft::write_meta(dir, &meta)?;
+    Ok(meta)
+}
+
+/// Stores the signed file of `bundle` for the local draft `local`, after checking the
+/// bundle was made from the same draft file and describes the same transaction, and
+/// that the signed file decodes to that transaction with every input signed.
+pub fn import_signed(
+    dir: &Path,
+    local: &NockchainTxMeta,
+    bundle: Bundle,
+) -> Result<NockchainTxMeta, String> {
+    let (signed, contents) = signed_contents(dir, local, &bundle)?;
+    let draft = inspect::read(&draft::draft_path(dir, &local.draft_id))?;
+    if contents.name != draft.name || !same_inputs(&draft, &contents) {
+        return Err("bundle's signed file is a different transaction than the draft".to_string());
+    }
+    if let Some(input) = contents
+        .inputs
+        .iter()
+        .find(|input| input.signed_by.is_empty())
+    {
+        return Err(format!(
+            "bundle's signed file has no signature over the spend of {}",
+            input.name
+        ));
+    }
+
+    fs::write(draft::signed_path(dir, &local.draft_id), signed)
+        .map_err(|e| format!("failed to write signed file: {}", e))?;
+    let meta = NockchainTxMeta {
+        status: NockchainTxStatus::Signed,
+        signed_at: bundle.meta.signed_at.or_else(|| Some(now_nanos())),
+        ..local.clone()
+    };
+    draft::write_meta(dir, &meta)?;
+    Ok(meta)
+}
+
+/// Merges the signatures of a multisig draft signed by cosigners on another install
+/// into the local draft `local`, whose current file is at `current`. The signed file
+/// of `bundle` has to be the same transaction holding every signature of the current
+/// file and more, all by keys of `lock`. The draft is signed once `lock`'s threshold
+/// of keys signed every note.
+pub fn import_cosigned(
+    dir: &Path,
+    local: &NockchainTxMeta,
+    current: &Path,
+    lock: &Lock,
+    bundle: Bundle,
+) -> Result<NockchainTxMeta, String> {
+    let (signed, contents) = signed_contents(dir, local, &bundle)?;
+    let current = inspect::read(current)?;
+    let signed_by =
+        inspect::check_signed_over(&current, &contents).map_err(|e| format!("bundle's {}", e))?;
+    for input in &contents.inputs {
+        for key in &input.signed_by {
+            if !lock.keys().iter().any(|known| known.to_string() == *key) {
+                return Err(format!("{} is not one of the multisig keys", key));
+            }
+        }
+    }
+
+    fs::write(draft::signed_path(dir, &local.draft_id), signed)
+        .map_err(|e| format!("failed to write signed file: {}", e))?;
+    let mut meta = NockchainTxMeta {
+        status: NockchainTxStatus::Cosigning,
+        signed_by,
+        ..local.clone()
+    };
+    if meta.signed_by.len() as u64 >= lock.threshold() {
+        meta.status = NockchainTxStatus::Signed;
+        meta.signed_at = Some(now_nanos());
+    }
+    draft::write_meta(dir, &meta)?;
+    Ok(meta)
+}
+
+// the signed file of `bundle` and its decoded contents, after checking the bundle was
+// made from the draft file of `local`, describes the same transaction, and that the
+// signed file decodes to that transaction
+fn signed_contents(
+    dir: &Path,
+    local: &NockchainTxMeta,
+    bundle: &Bundle,
+) -> Result<(Vec<u8>, DraftContents), String> {
+    let Some(signed) = &bundle.signed else {
+        return Err("bundle does not contain a signed transaction".to_string());
+    };
+    let local_draft = fs::read(draft::draft_path(dir, &local.draft_id))
+        .map_err(|e| format!("failed to read draft file: {}", e))?;
+    if digest(&local_draft) != digest(&bundle.draft) {
+        return Err("bundle was made from a different draft file".to_string());
+    }
+    if !same_transaction(local, &bundle.meta) {
+        return Err("bundle describes a different transaction than the draft".to_string());
+    }
+    if signed.is_empty() || *signed == local_draft {
+        return Err("bundle's signed file is not a signed transaction".to_string());
+    }
+    let contents =
+        inspect::decode(signed.clone()).map_err(|e| format!("bundle's signed file: {}", e))?;
+    inspect::check(local, &contents)
+        .map_err(|e| format!("bundle's signed file does not match the draft: {}", e))?;
+    Ok((signed.clone(), contents))
+}
+
+// draft ids name files, so only plain ids are taken from a bundle
+fn check_draft_id(draft_id: &str) -> Result<(), String> {
+    if draft_id.is_empty()
+        || draft_id.len() > MAX_DRAFT_ID_LEN
+        || !draft_id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    {
+        return Err(format!("invalid draft id {:?}", draft_id));
+    }
+    Ok(())
+}
+
+fn same_transaction(a: &NockchainTxMeta, b: &NockchainTxMeta) -> bool {
+    a.fee == b.fee
+        && a.inputs == b.inputs
+        && a.transactions.len() == b.transactions.len()
+        && a.transactions
+            .iter()
+            .zip(&b.transactions)
+            .all(|(a, b)| a.recipient == b.recipient && a.amount == b.amount)
+}
+
+fn same_inputs(a: &DraftContents, b: &DraftContents) -> bool {
+    let mut a: Vec<&str> = a.inputs.iter().map(|i| i.name.as_str()).collect();
+    let mut b: Vec<&str> = b.inputs.iter().map(|i| i.name.as_str()).collect();
+    a.sort_unstable();
+    b.sort_unstable();
+    a == b
+}
+
+fn decode(name: &str, base64: &str, hash: &str) -> Result<Vec<u8>, String> {
+    let bytes = general_purpose::STANDARD
+        .decode(base64)
+        .map_err(|e| format!("bundle {} file is not valid base64: {}", name, e))?;
+    if digest(&bytes) != hash.to_lowercase() {
+        return Err(format!(
+            "bundle {} file does not match its digest, the bundle is damaged",
+            name
+        ));
+    }
+    Ok(bytes)
+}
+
+fn digest(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
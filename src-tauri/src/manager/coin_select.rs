@@ -0,0 +1,426 @@
+// manager/coin_select.rs
+//
+// Strategies for picking the notes that fund a transaction. A selector gets the
+// spendable notes with their values and returns the indices of the notes to spend,
+// which together cover the target (amount + fee), or an error if they can't.
+
+use serde::{Deserialize, Serialize};
+
+use super::wallet::Note;
+
+/// Upper bound on the combinations branch-and-bound explores before giving up.
+const BNB_MAX_TRIES: usize = 100_000;
+
+/// A spendable note with its value in nicks.
+#[derive(Clone, Debug)]
+pub struct Candidate<'a> {
+    pub note: &'a Note,
+    pub value: u64,
+    /// position in the order the wallet first saw its notes, `None` if unknown
+    pub first_seen: Option<usize>,
+}
+
+pub trait CoinSelector: Send + Sync {
+    /// Returns the indices into `candidates` of the notes to spend for `target`.
+    fn select(&self, candidates: &[Candidate], target: u64) -> Result<Vec<usize>, String>;
+}
+
+/// The selection strategy of a transaction, as sent by the frontend.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CoinSelection {
+    /// fewest notes, the previous default
+    #[default]
+    LargestFirst,
+    /// spends small notes first, consolidating dust
+    SmallestFirst,
+    /// looks for notes that add up to the target exactly, so there is no change
+    BranchAndBound,
+    /// spends the notes the wallet has held longest first
+    OldestFirst,
+}
+
+impl CoinSelection {
+    pub fn selector(self) -> Box<dyn CoinSelector> {
+        match self {
+            CoinSelection::LargestFirst => Box::new(LargestFirst),
+            CoinSelection::SmallestFirst => Box::new(SmallestFirst),
+            CoinSelection::BranchAndBound => Box::new(BranchAndBound),
+            CoinSelection::OldestFirst => Box::new(OldestFirst),
+        }
+    }
+}
+
+pub struct LargestFirst;
+pub struct SmallestFirst;
+pub struct BranchAndBound;
+pub struct OldestFirst;
+
+/// Spends exactly the notes picked by the user, given by name as `[first last]`.
+pub struct Explicit {
+    names: Vec<String>,
+}
+
+impl Explicit {
+    pub fn new(names: Vec<String>) -> Self {
+        // tolerate extra whitespace in names typed or pasted by hand
+        let names = names
+            .iter()
+            .map(|name| name.split_whitespace().collect::<Vec<&str>>().join(" "))
+            .collect();
+        Self { names }
+    }
+}
+
+impl CoinSelector for LargestFirst {
+    fn select(&self, candidates: &[Candidate], target: u64) -> Result<Vec<usize>, String> {
+        let mut order = (0..candidates.len()).collect::<Vec<usize>>();
+        order.sort_by(|a, b| candidates[*b].value.cmp(&candidates[*a].value));
+        accumulate(candidates, order, target)
+    }
+}
+
+impl CoinSelector for SmallestFirst {
+    fn select(&self, candidates: &[Candidate], target: u64) -> Result<Vec<usize>, String> {
+        let mut order = (0..candidates.len()).collect::<Vec<usize>>();
+        order.sort_by_key(|i| candidates[*i].value);
+        accumulate(candidates, order, target)
+    }
+}
+
+impl CoinSelector for OldestFirst {
+    fn select(&self, candidates: &[Candidate], target: u64) -> Result<Vec<usize>, String> {
+        let mut order = (0..candidates.len()).collect::<Vec<usize>>();
+        // notes of unknown age go last
+        order.sort_by_key(|i| candidates[*i].first_seen.unwrap_or(usize::MAX));
+        accumulate(candidates, order, target)
+    }
+}
+
+impl CoinSelector for Explicit {
+    fn select(&self, candidates: &[Candidate], target: u64) -> Result<Vec<usize>, String> {
+        if target == 0 {
+            return Err("nothing to spend".to_string());
+        }
+        if self.names.is_empty() {
+            return Err("no notes selected".to_string());
+        }
+        let mut selected = Vec::new();
+        for name in &self.names {
+            let Some(i) = candidates.iter().position(|c| c.note.name() == *name) else {
+                return Err(format!(
+                    "note {} is not a spendable note of this wallet, it may be reserved by another draft",
+                    name
+                ));
+            };
+            if selected.contains(&i) {
+                return Err(format!("note {} is selected more than once", name));
+            }
+            selected.push(i);
+        }
+        let sum = selected
+            .iter()
+            .fold(0u64, |sum, i| sum.saturating_add(candidates[*i].value));
+        if sum < target {
+            return Err(format!(
+                "selected notes hold {} nicks but {} are needed",
+                sum, target
+            ));
+        }
+        Ok(selected)
+    }
+}
+
+impl CoinSelector for BranchAndBound {
+    fn select(&self, candidates: &[Candidate], target: u64) -> Result<Vec<usize>, String> {
+        check_total(candidates, target)?;
+        let mut order = (0..candidates.len())
+            .filter(|i| candidates[*i].value > 0)
+            .collect::<Vec<usize>>();
+        order.sort_by(|a, b| candidates[*b].value.cmp(&candidates[*a].value));
+        let values = order
+            .iter()
+            .map(|i| candidates[*i].value)
+            .collect::<Vec<u64>>();
+        // remaining[i] is the sum of values[i..], to prune branches that can't reach the target
+        let mut remaining = vec![0u64; values.len() + 1];
+        for i in (0..values.len()).rev() {
+            remaining[i] = remaining[i + 1].saturating_add(values[i]);
+        }
+
+        let mut search = Search {
+            values: &values,
+            remaining: &remaining,
+            target,
+            tries: 0,
+            picked: Vec::new(),
+        };
+        if search.run(0, 0) {
+            return Ok(search.picked.into_iter().map(|i| order[i]).collect());
+        }
+        Err(
+            "no combination of notes matches the amount exactly, choose another selection strategy"
+                .to_string(),
+        )
+    }
+}
+
+// depth-first search over include/exclude decisions, largest notes first
+struct Search<'a> {
+    values: &'a [u64],
+    remaining: &'a [u64],
+    target: u64,
+    tries: usize,
+    picked: Vec<usize>,
+}
+
+impl Search<'_> {
+    fn run(&mut self, index: usize, sum: u64) -> bool {
+        if sum == self.target {
+            return true;
+        }
+        self.tries += 1;
+        if index == self.values.len()
+            || self.tries > BNB_MAX_TRIES
+            || sum.saturating_add(self.remaining[index]) < self.target
+        {
+            return false;
+        }
+        let with = sum.saturating_add(self.values[index]);
+        if with <= self.target {
+            self.picked.push(index);
+            if self.run(index + 1, with) {
+                return true;
+            }
+            self.picked.pop();
+        }
+        self.run(index + 1, sum)
+    }
+}
+
+// takes notes in `order` until the target is covered
+fn accumulate(
+    candidates: &[Candidate],
+    order: Vec<usize>,
+    target: u64,
+) -> Result<Vec<usize>, String> {
+    check_total(candidates, target)?;
+    let mut selected = Vec::new();
+    let mut sum = 0u64;
+    for i in order {
+        if sum >= target {
+            break;
+        }
+        selected.push(i);
+        sum = sum.saturating_add(candidates[i].value);
+    }
+    Ok(selected)
+}
+
+fn check_total(candidates: &[Candidate], target: u64) -> Result<(), String> {
+    if target == 0 {
+        return Err("nothing to spend".to_string());
+    }
+    let total = candidates
+        .iter()
+        .fold(0u64, |sum, c| sum.saturating_add(c.value));
+    if total < target {
+        return Err("insufficient funds in available notes".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::Amount;
+
+    // xorshift, so the cases are the same on every run
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, bound: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % bound
+        }
+    }
+
+    fn notes(values: &[u64]) -> Vec<Note> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| Note {
+                first: format!("first{}", i),
+                last: format!("last{}", i),
+                assets: Amount::from_nicks(*value),
+            })
+            .collect()
+    }
+
+    fn candidates(notes: &[Note]) -> Vec<Candidate> {
+        notes
+            .iter()
+            .enumerate()
+            .map(|(i, note)| Candidate {
+                note,
+                value: note.assets.nicks(),
+                // every third note of unknown age
+                first_seen: (i % 3 != 0).then_some(notes.len() - i),
+            })
+            .collect()
+    }
+
+    fn strategies(notes: &[Note], rng: &mut Rng) -> Vec<(&'static str, Box<dyn CoinSelector>)> {
+        let names = notes
+            .iter()
+            .filter(|_| rng.next(2) == 0)
+            .map(Note::name)
+            .collect();
+        vec![
+            ("largest first", CoinSelection::LargestFirst.selector()),
+            ("smallest first", CoinSelection::SmallestFirst.selector()),
+            ("branch and bound", CoinSelection::BranchAndBound.selector()),
+            ("oldest first", CoinSelection::OldestFirst.selector()),
+            ("explicit", Box::new(Explicit::new(names))),
+        ]
+    }
+
+    // the selection is distinct indices into `candidates` covering `target`
+    fn assert_valid(name: &str, candidates: &[Candidate], target: u64, selected: &[usize]) {
+        let mut distinct = selected.to_vec();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(distinct.len(), selected.len(), "{}: repeated index", name);
+        assert!(
+            selected.iter().all(|i| *i < candidates.len()),
+            "{}: index out of range",
+            name
+        );
+        let sum = selected.iter().map(|i| candidates[*i].value).sum::<u64>();
+        assert!(sum >= target, "{}: {} does not cover {}", name, sum, target);
+    }
+
+    #[test]
+    fn selections_cover_the_target_or_fail() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..500 {
+            let count = rng.next(12) as usize;
+            let values = (0..count).map(|_| rng.next(1000)).collect::<Vec<u64>>();
+            let total = values.iter().sum::<u64>();
+            let target = rng.next(total + 100);
+            let notes = notes(&values);
+            let candidates = candidates(&notes);
+            for (name, selector) in strategies(&notes, &mut rng) {
+                if let Ok(selected) = selector.select(&candidates, target) {
+                    assert_valid(name, &candidates, target, &selected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn greedy_strategies_succeed_when_funds_suffice() {
+        let mut rng = Rng(42);
+        for _ in 0..200 {
+            let values = (0..8).map(|_| rng.next(1000) + 1).collect::<Vec<u64>>();
+            let target = rng.next(values.iter().sum::<u64>()) + 1;
+            let notes = notes(&values);
+            let candidates = candidates(&notes);
+            for selection in [
+                CoinSelection::LargestFirst,
+                CoinSelection::SmallestFirst,
+                CoinSelection::OldestFirst,
+            ] {
+                let selected = selection.selector().select(&candidates, target).unwrap();
+                assert_valid("greedy", &candidates, target, &selected);
+            }
+        }
+    }
+
+    #[test]
+    fn branch_and_bound_finds_exact_matches() {
+        let notes = notes(&[50, 30, 20, 7, 3]);
+        let candidates = candidates(&notes);
+        let selected = BranchAndBound.select(&candidates, 53).unwrap();
+        let sum = selected.iter().map(|i| candidates[*i].value).sum::<u64>();
+        assert_eq!(sum, 53);
+        assert!(BranchAndBound.select(&candidates, 2).is_err());
+    }
+
+    #[test]
+    fn insufficient_funds_fail() {
+        let notes = notes(&[10, 20, 30]);
+        let candidates = candidates(&notes);
+        let mut rng = Rng(7);
+        for (name, selector) in strategies(&notes, &mut rng) {
+            assert!(selector.select(&candidates, 61).is_err(), "{}", name);
+        }
+        assert!(LargestFirst.select(&[], 1).is_err());
+    }
+
+    #[test]
+    fn zero_target_fails() {
+        let notes = notes(&[10, 20]);
+        let candidates = candidates(&notes);
+        let all = notes.iter().map(Note::name).collect();
+        assert!(LargestFirst.select(&candidates, 0).is_err());
+        assert!(SmallestFirst.select(&candidates, 0).is_err());
+        assert!(BranchAndBound.select(&candidates, 0).is_err());
+        assert!(OldestFirst.select(&candidates, 0).is_err());
+        assert!(Explicit::new(all).select(&candidates, 0).is_err());
+    }
+
+    #[test]
+    fn explicit_checks_the_picked_notes() {
+        let notes = notes(&[10, 20, 30]);
+        let candidates = candidates(&notes);
+        let picked = vec![notes[2].name(), notes[0].name()];
+        assert_eq!(
+            Explicit::new(picked).select(&candidates, 40).unwrap(),
+            vec![2, 0]
+        );
+        let spaced = vec![format!("  [first1   last1] ")];
+        assert_eq!(
+            Explicit::new(spaced).select(&candidates, 20).unwrap(),
+            vec![1]
+        );
+        assert!(Explicit::new(vec![notes[0].name()])
+            .select(&candidates, 11)
+            .is_err());
+        assert!(Explicit::new(vec![notes[0].name(), notes[0].name()])
+            .select(&candidates, 5)
+            .is_err());
+        assert!(Explicit::new(vec!["[nope nope]".to_string()])
+            .select(&candidates, 5)
+            .is_err());
+        assert!(Explicit::new(Vec::new()).select(&candidates, 5).is_err());
+    }
+
+    #[test]
+    fn branch_and_bound_gives_up_after_max_tries() {
+        // even values can't add up to an odd target, and pruning can't rule it out early
+        let values = (0..40).map(|i| 2 * (1000 + i)).collect::<Vec<u64>>();
+        let target = values.iter().sum::<u64>() / 2 + 1;
+        let mut search = Search {
+            values: &values,
+            remaining: &{
+                let mut remaining = vec![0u64; values.len() + 1];
+                for i in (0..values.len()).rev() {
+                    remaining[i] = remaining[i + 1] + values[i];
+                }
+                remaining
+            },
+            target,
+            tries: 0,
+            picked: Vec::new(),
+        };
+        assert!(!search.run(0, 0));
+        assert!(search.tries > BNB_MAX_TRIES);
+        assert!(search.tries <= BNB_MAX_TRIES + values.len() + 1);
+
+        let notes = notes(&values);
+        assert!(BranchAndBound.select(&candidates(&notes), target).is_err());
+    }
+}
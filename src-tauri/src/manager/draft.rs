@@ -0,0 +1,195 @@
+// manager/draft.rs
+//
+// On-disk bookkeeping for transaction drafts. Each `<id>.draft` / `<id>.signed`
+// file written by the kernel gets a `<id>.meta.json` sidecar with its
+// `NockchainTxMeta`, so the drafts of a wallet can be rebuilt after a restart.
+// Paths are not stored in the sidecar, the directory may move when a wallet is renamed.
+// The input notes of a draft are reserved for it until it is sent and resolved, or
+// cancelled, so that two drafts don't spend the same note.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::amount::Amount;
+
+use super::inspect;
+use super::wallet::{NockchainTxMeta, NockchainTxStatus, TransactionEntry};
+
+const DRAFT_EXT: &str = "draft";
+const SIGNED_EXT: &str = "signed";
+const META_SUFFIX: &str = ".meta.json";
+
+pub fn draft_path(dir: &Path, draft_id: &str) -> PathBuf {
+    dir.join(format!("{}.{}", draft_id, DRAFT_EXT))
+}
+
+pub fn signed_path(dir: &Path, draft_id: &str) -> PathBuf {
+    dir.join(format!("{}.{}", draft_id, SIGNED_EXT))
+}
+
+fn meta_path(dir: &Path, draft_id: &str) -> PathBuf {
+    dir.join(format!("{}{}", draft_id, META_SUFFIX))
+}
+
+/// Writes the sidecar of a draft.
+pub fn write_meta(dir: &Path, meta: &NockchainTxMeta) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(meta).map_err(|e| e.to_string())?;
+    fs::write(meta_path(dir, &meta.draft_id), content)
+        .map_err(|e| format!("failed to write draft metadata: {}", e))
+}
+
+/// Reads all drafts in `dir`, returning their metadata and the path of the newest
+/// transaction file (`.signed` if the draft was signed, `.draft` otherwise).
+///
+/// The directory is reconciled on the way: sidecars whose transaction files are gone
+/// are removed, transaction files without a sidecar get a new one (with the details
+/// that can't be recovered left empty), and a draft with a `.signed` file is marked signed.
+pub fn read_drafts(dir: &Path) -> Result<HashMap<String, (NockchainTxMeta, PathBuf)>, String> {
+    let mut drafts = HashMap::new();
+    if !dir.exists() {
+        return Ok(drafts);
+    }
+    let mut ids = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let name = entry.map_err(|e| e.to_string())?.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        let id = name
+            .strip_suffix(META_SUFFIX)
+            .or_else(|| name.strip_suffix(&format!(".{}", DRAFT_EXT)))
+            .or_else(|| name.strip_suffix(&format!(".{}", SIGNED_EXT)));
+        if let Some(id) = id {
+            if !ids.iter().any(|known| known == id) {
+                ids.push(id.to_string());
+            }
+        }
+    }
+
+    for id in ids {
+        let draft_file = draft_path(dir, &id);
+        let signed_file = signed_path(dir, &id);
+        let meta_file = meta_path(dir, &id);
+
+        let location = if signed_file.exists() {
+            signed_file
+        } else if draft_file.exists() {
+            draft_file
+        } else {
+            tracing::warn!("drafts: removing metadata of missing draft {}", id);
+            if let Err(e) = fs::remove_file(&meta_file) {
+                tracing::error!("drafts: failed to remove {:?}: {}", meta_file, e);
+            }
+            continue;
+        };
+        let signed = location.extension().and_then(|e| e.to_str()) == Some(SIGNED_EXT);
+
+        let (mut meta, mut changed) = match read_meta(&meta_file) {
+            Some(meta) => (meta, false),
+            None => {
+                tracing::warn!("drafts: rebuilding metadata of orphaned draft {}", id);
+                (orphan_meta(&id, &location), true)
+            }
+        };
+        if signed && matches!(meta.status, NockchainTxStatus::Draft) {
+            meta.status = NockchainTxStatus::Signed;
+            changed = true;
+        }
+        if changed {
+            write_meta(dir, &meta)?;
+        }
+        drafts.insert(id, (meta, location));
+    }
+    Ok(drafts)
+}
+
+fn read_meta(path: &Path) -> Option<NockchainTxMeta> {
+    let content = fs::read_to_string(path).ok()?;
+    match serde_json::from_str::<NockchainTxMeta>(&content) {
+        Ok(meta) => Some(meta),
+        Err(e) => {
+            tracing::error!("drafts: invalid metadata in {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+// metadata for a transaction file that lost its sidecar, with the inputs, fee and
+// recipients read back from the file when it can be decoded
+fn orphan_meta(draft_id: &str, location: &Path) -> NockchainTxMeta {
+    let created_at = fs::metadata(location)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos().to_string())
+        .unwrap_or_default();
+    let mut meta = NockchainTxMeta {
+        draft_id: draft_id.to_string(),
+        transactions: Vec::new(),
+        fee: Amount::ZERO,
+        created_at,
+        signed_at: None,
+        broadcasted_at: None,
+        status: NockchainTxStatus::Draft,
+        inputs: Vec::new(),
+        broadcast_height: None,
+        confirmed_height: None,
+        signed_by: Vec::new(),
+        inputs_missing_at: None,
+    };
+    match inspect::read(location) {
+        Ok(contents) => {
+            meta.transactions = contents
+                .outputs
+                .into_iter()
+                .map(|output| TransactionEntry {
+                    recipient: output.lock,
+                    amount: output.amount,
+                })
+                .collect();
+            meta.fee = contents.fee;
+            meta.inputs = contents
+                .inputs
+                .into_iter()
+                .map(|input| input.name)
+                .collect();
+        }
+        Err(e) => tracing::warn!("drafts: cannot decode orphaned draft {}: {}", draft_id, e),
+    }
+    meta
+}
+
+/// Whether the draft has not been broadcast yet.
+pub fn is_unsent(meta: &NockchainTxMeta) -> bool {
+    matches!(
+        meta.status,
+        NockchainTxStatus::Draft | NockchainTxStatus::Cosigning | NockchainTxStatus::Signed
+    )
+}
+
+/// Whether the draft holds its input notes, from its creation until its transaction
+/// is confirmed, fails or expires.
+pub fn reserves_inputs(meta: &NockchainTxMeta) -> bool {
+    is_unsent(meta) || matches!(meta.status, NockchainTxStatus::Pending)
+}
+
+/// Whether an unsent draft can't be sent anymore, because some of its input notes
+/// are no longer among the wallet's notes `current` (they were spent elsewhere).
+pub fn inputs_spent(meta: &NockchainTxMeta, current: &HashSet<String>) -> bool {
+    is_unsent(meta) && meta.inputs.iter().any(|input| !current.contains(input))
+}
+
+/// Deletes the transaction files and sidecar of a draft.
+pub fn remove(dir: &Path, draft_id: &str) -> Result<(), String> {
+    for path in [
+        draft_path(dir, draft_id),
+        signed_path(dir, draft_id),
+        meta_path(dir, draft_id),
+    ] {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("failed to remove {:?}: {}", path, e))?;
+        }
+    }
+    Ok(())
+}
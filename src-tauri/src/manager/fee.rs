@@ -0,0 +1,238 @@
+// manager/fee.rs
+//
+// Fee estimation. A transaction's fee is priced per input note and per output
+// (each recipient plus the change note), at a rate in nicks per input/output. Low
+// and high are half and double of the normal rate.
+//
+// The normal rate is a fixed default of this wallet, not chain data: the kernel
+// doesn't expose recent fees or a minimum fee to peek, and the wallet's own past
+// fees were set by this estimator, so they can't tell what the chain accepts. It
+// keeps the fee of a one-input, two-output transaction at 192 nicks, under 0.003
+// NOCK. A fixed fee can always be given instead.
+
+use std::fmt;
+
+use serde::de::{self, Deserializer, IntoDeserializer, Visitor};
+use serde::{Deserialize, Serialize};
+
+use crate::amount::Amount;
+
+use super::coin_select::{Candidate, CoinSelector};
+
+/// Nicks per input or output at the normal rate.
+pub const NORMAL_RATE: u64 = 64;
+
+/// The fee of a transaction as sent by the frontend, either an amount of nicks or `"auto"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeSpec {
+    Fixed(Amount),
+    /// the normal fee estimate
+    Auto,
+}
+
+impl<'de> Deserialize<'de> for FeeSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FeeSpecVisitor;
+
+        impl Visitor<'_> for FeeSpecVisitor {
+            type Value = FeeSpec;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a fee in nicks or \"auto\"")
+            }
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<FeeSpec, E> {
+                Ok(FeeSpec::Fixed(Amount::from_nicks(value)))
+            }
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<FeeSpec, E> {
+                Amount::deserialize(value.into_deserializer()).map(FeeSpec::Fixed)
+            }
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<FeeSpec, E> {
+                match value {
+                    "auto" => Ok(FeeSpec::Auto),
+                    amount => Amount::deserialize(amount.into_deserializer()).map(FeeSpec::Fixed),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(FeeSpecVisitor)
+    }
+}
+
+/// One fee option with the number of notes it would spend.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeOption {
+    pub fee: Amount,
+    pub inputs: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeEstimate {
+    pub low: FeeOption,
+    /// `None` if the wallet's notes can't cover it
+    pub normal: Option<FeeOption>,
+    pub high: Option<FeeOption>,
+    pub outputs: usize,
+    /// nicks per input or output of the normal option
+    pub rate: u64,
+}
+
+/// Finds the fee at `rate` for sending `amount` to `recipients` recipients, together
+/// with the notes the selector picks to cover it. The selection is redone until the
+/// fee covers the notes it selects, a larger fee can need more notes.
+pub fn settle(
+    candidates: &[Candidate],
+    selector: &dyn CoinSelector,
+    amount: Amount,
+    recipients: usize,
+    rate: u64,
+) -> Result<(Amount, Vec<usize>), String> {
+    let outputs = recipients + 1;
+    let mut fee = fee_for(1, outputs, rate)?;
+    loop {
+        let target = amount
+            .checked_add(fee)
+            .ok_or("amount plus fee overflows".to_string())?;
+        let selected = selector.select(candidates, target.nicks())?;
+        let needed = fee_for(selected.len(), outputs, rate)?;
+        if needed <= fee {
+            return Ok((fee, selected));
+        }
+        fee = needed;
+    }
+}
+
+/// Low, normal and high fee options for sending `amount` to `recipients` recipients.
+/// Fails if not even the low fee can be covered.
+pub fn estimate(
+    candidates: &[Candidate],
+    selector: &dyn CoinSelector,
+    amount: Amount,
+    recipients: usize,
+) -> Result<FeeEstimate, String> {
+    let rate = NORMAL_RATE;
+    let option = |rate: u64| {
+        settle(candidates, selector, amount, recipients, rate).map(|(fee, selected)| FeeOption {
+            fee,
+            inputs: selected.len(),
+        })
+    };
+    Ok(FeeEstimate {
+        low: option((rate / 2).max(1))?,
+        normal: option(rate).ok(),
+        high: option(rate.saturating_mul(2)).ok(),
+        outputs: recipients + 1,
+        rate,
+    })
+}
+
+fn fee_for(inputs: usize, outputs: usize, rate: u64) -> Result<Amount, String> {
+    Amount::from_nicks(rate)
+        .checked_mul((inputs + outputs) as u64)
+        .ok_or("fee overflows".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manager::coin_select::CoinSelection;
+    use crate::manager::wallet::Note;
+
+    fn notes(values: &[u64]) -> Vec<Note> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| Note {
+                first: format!("first{}", i),
+                last: format!("last{}", i),
+                assets: Amount::from_nicks(*value),
+            })
+            .collect()
+    }
+
+    fn candidates(notes: &[Note]) -> Vec<Candidate<'_>> {
+        notes
+            .iter()
+            .map(|note| Candidate {
+                note,
+                value: note.assets.nicks(),
+                first_seen: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn settle_covers_the_notes_it_selects() {
+        // small notes, so each extra note pushes the fee past what the last one added
+        let notes = notes(&[1_000; 40]);
+        let candidates = candidates(&notes);
+        let selector = CoinSelection::LargestFirst.selector();
+        for recipients in 1..4 {
+            for amount in [1, 999, 1_000, 5_000, 20_000] {
+                let amount = Amount::from_nicks(amount);
+                let (fee, selected) = settle(
+                    &candidates,
+                    selector.as_ref(),
+                    amount,
+                    recipients,
+                    NORMAL_RATE,
+                )
+                .unwrap();
+                assert_eq!(
+                    fee,
+                    fee_for(selected.len(), recipients + 1, NORMAL_RATE).unwrap(),
+                    "fee is priced for the notes selected"
+                );
+                let total: u64 = selected.iter().map(|i| candidates[*i].value).sum();
+                assert!(total >= amount.checked_add(fee).unwrap().nicks());
+            }
+        }
+    }
+
+    #[test]
+    fn settle_fails_when_the_fee_cant_be_covered() {
+        let notes = notes(&[1_000; 3]);
+        let candidates = candidates(&notes);
+        let selector = CoinSelection::LargestFirst.selector();
+        let amount = Amount::from_nicks(2_900);
+        assert!(settle(&candidates, selector.as_ref(), amount, 1, NORMAL_RATE).is_err());
+        assert!(settle(&candidates, selector.as_ref(), amount, 1, 1).is_ok());
+    }
+
+    #[test]
+    fn estimate_options_are_ordered() {
+        let notes = notes(&[500, 1_000, 2_000, 4_000, 8_000, 16_000]);
+        let candidates = candidates(&notes);
+        let selector = CoinSelection::SmallestFirst.selector();
+        let options = estimate(
+            &candidates,
+            selector.as_ref(),
+            Amount::from_nicks(10_000),
+            2,
+        )
+        .unwrap();
+        let normal = options.normal.unwrap();
+        let high = options.high.unwrap();
+        assert!(options.low.fee < normal.fee);
+        assert!(normal.fee < high.fee);
+        assert!(options.low.inputs <= normal.inputs && normal.inputs <= high.inputs);
+        assert_eq!(options.rate, NORMAL_RATE);
+        assert_eq!(options.outputs, 3);
+    }
+
+    #[test]
+    fn estimate_drops_options_the_notes_cant_cover() {
+        // 9_000 covers the amount with the low and normal fees but not with the high one
+        let notes = notes(&[3_000; 3]);
+        let candidates = candidates(&notes);
+        let selector = CoinSelection::LargestFirst.selector();
+        let options =
+            estimate(&candidates, selector.as_ref(), Amount::from_nicks(8_500), 1).unwrap();
+        assert!(options.normal.unwrap().fee > options.low.fee);
+        assert!(options.high.is_none());
+
+        let too_much = Amount::from_nicks(9_000);
+        assert!(estimate(&candidates, selector.as_ref(), too_much, 1).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+// manager/history.rs
+//
+// Per-wallet transaction history, kept in `history_dir/<wallet>/history.json`.
+// Sent transactions are recorded when they are broadcast and resolved against the
+// wallet's notes on later syncs; notes that appear between two syncs are recorded
+// as received, except the change of the wallet's own transactions. A history file
+// that can't be read is moved aside and a new one started.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::amount::Amount;
+use crate::timestamp::now_nanos;
+
+use super::wallet::{NockchainTxMeta, NockchainTxStatus, Note};
+
+/// Blocks after broadcast after which a transaction none of whose inputs were spent is given up on.
+pub const EXPIRY_BLOCKS: u32 = 100;
+
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+pub const MAX_PAGE_SIZE: usize = 100;
+
+const HISTORY_FILE: &str = "history.json";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceivedNote {
+    /// note name as `[first last]`
+    pub name: String,
+    pub assets: Amount,
+    /// `None` for notes the wallet already held when history tracking started
+    pub height: Option<u32>,
+    pub received_at: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "direction", rename_all = "lowercase")]
+pub enum HistoryEntry {
+    Sent(NockchainTxMeta),
+    Received(ReceivedNote),
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    pub total: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HistoryFile {
+    sent: Vec<NockchainTxMeta>,
+    received: Vec<ReceivedNote>,
+    /// note names seen on the last sync, `None` before the first one
+    known_notes: Option<Vec<String>>,
+}
+
+#[derive(Debug)]
+pub struct History {
+    path: PathBuf,
+    file: HistoryFile,
+}
+
+impl History {
+    /// Opens the history stored in `dir`, starting an empty one if there is none or
+    /// if it can't be parsed, in which case the file is kept as `history.json.corrupt-<ts>`.
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(HISTORY_FILE);
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Ok(Self {
+                path,
+                file: HistoryFile::default(),
+            });
+        };
+        let file = match serde_json::from_str(&content) {
+            Ok(file) => file,
+            Err(e) => {
+                let corrupt = dir.join(format!("{}.corrupt-{}", HISTORY_FILE, now_nanos()));
+                tracing::error!(
+                    "history: invalid history file, moving it to {:?}: {}",
+                    corrupt,
+                    e
+                );
+                fs::rename(&path, &corrupt)
+                    .map_err(|e| format!("failed to move invalid history aside: {}", e))?;
+                HistoryFile::default()
+            }
+        };
+        Ok(Self { path, file })
+    }
+
+    /// Adds or replaces the entry of a sent transaction.
+    pub fn record_sent(&mut self, meta: &NockchainTxMeta) -> Result<(), String> {
+        match self
+            .file
+            .sent
+            .iter_mut()
+            .find(|sent| sent.draft_id == meta.draft_id)
+        {
+            Some(sent) => *sent = meta.clone(),
+            None => self.file.sent.push(meta.clone()),
+        }
+        self.save()
+    }
+
+    /// Records the notes that appeared since the last sync as received, leaving out
+    /// the change of sent transactions whose inputs were spent since then.
+    pub fn record_notes(&mut self, notes: &[Note], height: Option<u32>) -> Result<(), String> {
+        let first_sync = self.file.known_notes.is_none();
+        let known = self
+            .file
+            .known_notes
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<HashSet<String>>();
+        let current = notes.iter().map(Note::name).collect::<HashSet<String>>();
+        let mut change = self.expected_change(&known, &current);
+        let now = now_nanos();
+        for note in notes {
+            let name = note.name();
+            if known.contains(&name) {
+                continue;
+            }
+            if let Some(i) = change.iter().position(|amount| *amount == note.assets) {
+                tracing::info!("history: note {} is change of a sent transaction", name);
+                change.remove(i);
+                continue;
+            }
+            self.file.received.push(ReceivedNote {
+                name,
+                assets: note.assets,
+                height: if first_sync { None } else { height },
+                received_at: now.clone(),
+            });
+        }
+        self.file.known_notes = Some(notes.iter().map(Note::name).collect());
+        self.save()
+    }
+
+    // the change amounts of the pending transactions whose inputs were all spent
+    // since the last sync, for those whose input values are known
+    fn expected_change(&self, known: &HashSet<String>, current: &HashSet<String>) -> Vec<Amount> {
+        let values = self
+            .file
+            .received
+            .iter()
+            .map(|note| (note.name.as_str(), note.assets))
+            .collect::<HashMap<&str, Amount>>();
+        self.file
+            .sent
+            .iter()
+            .filter(|meta| matches!(meta.status, NockchainTxStatus::Pending))
+            .filter(|meta| !meta.inputs.is_empty())
+            .filter(|meta| {
+                meta.inputs
+                    .iter()
+                    .all(|input| known.contains(input) && !current.contains(input))
+            })
+            .filter_map(|meta| {
+                let inputs = meta
+                    .inputs
+                    .iter()
+                    .map(|input| values.get(input.as_str()).copied())
+                    .collect::<Option<Vec<Amount>>>()?;
+                let spent = Amount::checked_sum(meta.transactions.iter().map(|tx| tx.amount))?
+                    .checked_add(meta.fee)?;
+                Amount::checked_sum(inputs)?.checked_sub(spent)
+            })
+            .filter(|change| *change != Amount::ZERO)
+            .collect()
+    }
+
+    /// Position of each received note in the order the wallet first saw them.
+    pub fn received_order(&self) -> HashMap<String, usize> {
+        self.file
+            .received
+            .iter()
+            .enumerate()
+            .map(|(i, note)| (note.name.clone(), i))
+            .collect()
+    }
+
+    /// Returns entries newest first, skipping `offset` and returning at most `limit`.
+    pub fn page(&self, offset: usize, limit: usize) -> HistoryPage {
+        let mut entries = self
+            .file
+            .sent
+            .iter()
+            .cloned()
+            .map(HistoryEntry::Sent)
+            .chain(
+                self.file
+                    .received
+                    .iter()
+                    .cloned()
+                    .map(HistoryEntry::Received),
+            )
+            .collect::<Vec<HistoryEntry>>();
+        entries.sort_by_key(|entry| std::cmp::Reverse(timestamp(entry)));
+        HistoryPage {
+            total: entries.len(),
+            entries: entries.into_iter().skip(offset).take(limit).collect(),
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let content = serde_json::to_string(&self.file).map_err(|e| e.to_string())?;
+        fs::write(&self.path, content).map_err(|e| format!("failed to write history: {}", e))
+    }
+}
+
+/// Works out what happened to a broadcast transaction from the wallet's current notes:
+/// confirmed once all of its inputs are spent, failed if only some of them were spent
+/// (by a conflicting transaction), expired if none were spent for `EXPIRY_BLOCKS`.
+/// Returns `None` while it is still pending.
+pub fn resolve(meta: &NockchainTxMeta, notes: &[Note], height: u32) -> Option<NockchainTxStatus> {
+    if !matches!(meta.status, NockchainTxStatus::Pending) || meta.inputs.is_empty() {
+        return None;
+    }
+    let Some(broadcast_height) = meta.broadcast_height else {
+        return None;
+    };
+    // the inputs can't be spent before the block after the broadcast
+    if height <= broadcast_height {
+        return None;
+    }
+    let current = notes.iter().map(Note::name).collect::<HashSet<String>>();
+    let spent = meta
+        .inputs
+        .iter()
+        .filter(|input| !current.contains(*input))
+        .count();
+    if spent == meta.inputs.len() {
+        Some(NockchainTxStatus::Confirmed)
+    } else if spent > 0 {
+        Some(NockchainTxStatus::Failed)
+    } else if height - broadcast_height > EXPIRY_BLOCKS {
+        Some(NockchainTxStatus::Expired)
+    } else {
+        None
+    }
+}
+
+fn timestamp(entry: &HistoryEntry) -> u128 {
+    let timestamp = match entry {
+        HistoryEntry::Sent(meta) => meta.broadcasted_at.as_ref().unwrap_or(&meta.created_at),
+        HistoryEntry::Received(note) => &note.received_at,
+    };
+    timestamp.parse().unwrap_or(0)
+}
@@ -0,0 +1,707 @@
+// manager/inspect.rs
+//
+// Reads the transaction files written by the wallet kernel, so a draft can be
+// looked at and checked before it is signed. A `.draft` or `.signed` file is a
+// jammed `transaction`, `[name inputs]`, where `inputs` maps the name of each spent
+// note to the note and its spend: the signatures so far, the seeds giving amounts
+// (gifts) to recipient locks, and the fee. Outputs locked to the same lock as the
+// spent notes are the change. Hashes and pubkeys are written in the base58 forms
+// the kernel uses for note names and addresses, so they compare with what the
+// wallet shows and what the user entered. The locks of the wallet's own notes are
+// read the same way from the kernel's state.
+
+use std::fs;
+use std::path::Path;
+
+use bytes::Bytes;
+use nockapp::noun::slab::NounSlab;
+use nockvm::noun::Noun;
+use serde::{Deserialize, Serialize};
+
+use super::wallet::NockchainTxMeta;
+use crate::amount::Amount;
+use crate::decode::{DecodeError, FromNoun, Tree};
+use crate::pubkey::{Lock, Pubkey};
+
+/// Goldilocks prime, the base of the digits of a hash.
+const P: u64 = 0xffff_ffff_0000_0001;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DraftInput {
+    /// name of the spent note, `[first last]`
+    pub name: String,
+    pub value: Amount,
+    /// pubkeys with a signature over the spend of this note
+    pub signed_by: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DraftOutput {
+    pub lock: String,
+    pub amount: Amount,
+}
+
+/// The decoded contents of a transaction file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DraftContents {
+    pub name: String,
+    pub inputs: Vec<DraftInput>,
+    /// amounts sent to each recipient lock, change excluded
+    pub outputs: Vec<DraftOutput>,
+    pub fee: Amount,
+    /// lock of the spent notes, the change goes back to it
+    pub lock: String,
+    /// amount returned to the lock of the spent notes
+    pub change: Amount,
+}
+
+/// Decodes the transaction file at `path`.
+pub fn read(path: &Path) -> Result<DraftContents, String> {
+    let jammed = fs::read(path).map_err(|e| format!("failed to read transaction file: {}", e))?;
+    decode(jammed)
+}
+
+/// Decodes the contents of a transaction file.
+pub fn decode(jammed: Vec<u8>) -> Result<DraftContents, String> {
+    let mut slab = NounSlab::new();
+    let noun = slab
+        .cue_into(Bytes::from(jammed))
+        .map_err(|e| format!("transaction file is not a jammed noun: {:?}", e))?;
+    let transaction =
+        Transaction::from_noun(&noun).map_err(|e| format!("unexpected transaction file: {}", e))?;
+    contents(transaction)
+}
+
+/// Checks that decoded contents are the transaction described by `meta`: the same
+/// notes spent, the same fee, the recipients paid exactly their amounts and the
+/// rest of the inputs returned as change.
+pub fn check(meta: &NockchainTxMeta, contents: &DraftContents) -> Result<(), String> {
+    let mut spent: Vec<&str> = contents.inputs.iter().map(|i| i.name.as_str()).collect();
+    let mut expected: Vec<&str> = meta.inputs.iter().map(String::as_str).collect();
+    spent.sort_unstable();
+    expected.sort_unstable();
+    if spent != expected {
+        return Err(format!(
+            "draft spends {} but {} were selected",
+            spent.join(", "),
+            expected.join(", ")
+        ));
+    }
+    if contents.fee != meta.fee {
+        return Err(format!(
+            "draft pays a fee of {} nicks but {} were set",
+            contents.fee.nicks(),
+            meta.fee.nicks()
+        ));
+    }
+
+    // sum the amounts of each recipient, one may be listed more than once
+    let mut payments: Vec<(Lock, Amount)> = Vec::new();
+    for tx in &meta.transactions {
+        let lock = Lock::parse(&tx.recipient)?;
+        match payments.iter_mut().find(|(known, _)| *known == lock) {
+            Some((_, amount)) => {
+                *amount = amount
+                    .checked_add(tx.amount)
+                    .ok_or("total amount overflows".to_string())?
+            }
+            None => payments.push((lock, tx.amount)),
+        }
+    }
+    for output in &contents.outputs {
+        let lock = Lock::parse(&output.lock)?;
+        match payments.iter().position(|(known, _)| *known == lock) {
+            Some(i) if payments[i].1 == output.amount => {
+                payments.remove(i);
+            }
+            Some(i) => {
+                return Err(format!(
+                    "draft sends {} nicks to {} but {} were set",
+                    output.amount.nicks(),
+                    lock,
+                    payments[i].1.nicks()
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "draft sends {} nicks to {}, which is not a recipient",
+                    output.amount.nicks(),
+                    lock
+                ))
+            }
+        }
+    }
+
+    // what's left are payments to the wallet itself, they are part of the change
+    let own_lock = Lock::parse(&contents.lock)?;
+    if let Some((lock, _)) = payments.iter().find(|(lock, _)| *lock != own_lock) {
+        return Err(format!("draft is missing the payment to {}", lock));
+    }
+    let outputs = Amount::checked_sum(contents.outputs.iter().map(|o| o.amount))
+        .and_then(|total| total.checked_add(contents.change))
+        .and_then(|total| total.checked_add(contents.fee))
+        .ok_or("draft amounts overflow".to_string())?;
+    let inputs = Amount::checked_sum(contents.inputs.iter().map(|i| i.value))
+        .ok_or("draft amounts overflow".to_string())?;
+    if outputs != inputs {
+        return Err(format!(
+            "draft spends {} nicks but its outputs and fee add up to {}",
+            inputs.nicks(),
+            outputs.nicks()
+        ));
+    }
+    let to_self = Amount::checked_sum(payments.iter().map(|(_, amount)| *amount))
+        .ok_or("total amount overflows".to_string())?;
+    if to_self > contents.change {
+        return Err(format!(
+            "draft returns {} nicks to {} but {} were sent to it",
+            contents.change.nicks(),
+            own_lock,
+            to_self.nicks()
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that `signed` is the transaction of `current` with more signatures: the
+/// same notes spent to the same outputs, every signature of `current` still there
+/// and at least one more. Returns the pubkeys that signed the spend of every note.
+pub fn check_signed_over(
+    current: &DraftContents,
+    signed: &DraftContents,
+) -> Result<Vec<String>, String> {
+    if unsigned(current) != unsigned(signed) {
+        return Err("signed file is a different transaction".to_string());
+    }
+    let mut added = 0;
+    for input in &current.inputs {
+        let Some(other) = signed.inputs.iter().find(|other| other.name == input.name) else {
+            return Err(format!("signed file does not spend {}", input.name));
+        };
+        if let Some(key) = input
+            .signed_by
+            .iter()
+            .find(|key| !other.signed_by.contains(key))
+        {
+            return Err(format!(
+                "signed file is missing the signature of {} over {}",
+                key, input.name
+            ));
+        }
+        added += other.signed_by.len() - input.signed_by.len();
+    }
+    if added == 0 {
+        return Err("signed file has no new signatures".to_string());
+    }
+    let Some((first, rest)) = signed.inputs.split_first() else {
+        return Ok(Vec::new());
+    };
+    Ok(first
+        .signed_by
+        .iter()
+        .filter(|key| rest.iter().all(|input| input.signed_by.contains(key)))
+        .cloned()
+        .collect())
+}
+
+// the contents without their signatures, in a fixed order
+fn unsigned(contents: &DraftContents) -> DraftContents {
+    let mut contents = contents.clone();
+    for input in contents.inputs.iter_mut() {
+        input.signed_by.clear();
+    }
+    contents.inputs.sort_by(|a, b| a.name.cmp(&b.name));
+    contents
+        .outputs
+        .sort_by(|a, b| (&a.lock, a.amount).cmp(&(&b.lock, b.amount)));
+    contents
+}
+
+fn contents(transaction: Transaction) -> Result<DraftContents, String> {
+    let mut inputs = Vec::new();
+    let mut own_lock: Option<Lock> = None;
+    let mut outputs: Vec<(Lock, Amount)> = Vec::new();
+    let mut fee = Amount::ZERO;
+    for (name, input) in transaction.inputs {
+        match &own_lock {
+            Some(lock) if *lock != input.lock => {
+                return Err("draft spends notes with different locks".to_string())
+            }
+            Some(_) => {}
+            None => own_lock = Some(input.lock),
+        }
+        inputs.push(DraftInput {
+            name,
+            value: Amount::from_nicks(input.assets),
+            signed_by: input.signers.iter().map(Pubkey::to_string).collect(),
+        });
+        fee = fee
+            .checked_add(Amount::from_nicks(input.fee))
+            .ok_or("draft fee overflows".to_string())?;
+        for (lock, gift) in input.seeds {
+            let gift = Amount::from_nicks(gift);
+            match outputs.iter_mut().find(|(known, _)| *known == lock) {
+                Some((_, amount)) => {
+                    *amount = amount
+                        .checked_add(gift)
+                        .ok_or("draft amounts overflow".to_string())?
+                }
+                None => outputs.push((lock, gift)),
+            }
+        }
+    }
+
+    let Some(own_lock) = own_lock else {
+        return Err("draft spends no notes".to_string());
+    };
+    let mut change = Amount::ZERO;
+    let mut sent = Vec::new();
+    for (lock, amount) in outputs {
+        if lock == own_lock {
+            change = change
+                .checked_add(amount)
+                .ok_or("draft amounts overflow".to_string())?;
+        } else {
+            sent.push(DraftOutput {
+                lock: lock.to_string(),
+                amount,
+            });
+        }
+    }
+    Ok(DraftContents {
+        name: transaction.name,
+        inputs,
+        outputs: sent,
+        fee,
+        lock: own_lock.to_string(),
+        change,
+    })
+}
+
+struct Transaction {
+    name: String,
+    inputs: Vec<(String, Input)>,
+}
+
+// `[name=@t inputs=(z-map nname input)]`
+impl FromNoun for Transaction {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let (name, Tree(inputs)) = <(String, Tree<(NoteName, Input)>)>::from_noun(noun)?;
+        Ok(Self {
+            name,
+            inputs: inputs
+                .into_iter()
+                .map(|(NoteName(name), input)| (name, input))
+                .collect(),
+        })
+    }
+}
+
+struct Input {
+    lock: Lock,
+    assets: u64,
+    signers: Vec<Pubkey>,
+    seeds: Vec<(Lock, u64)>,
+    fee: u64,
+}
+
+// `[note spend]`, with the spend `[signature=(unit (z-map schnorr-pubkey signature))
+// seeds fee]`
+impl FromNoun for Input {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let (note, spend) = <(Note, Noun)>::from_noun(noun)?;
+        let (signature, (Tree(seeds), fee)) =
+            <(Option<Tree<(PointKey, Noun)>>, (Tree<Seed>, u64))>::from_noun(&spend)
+                .map_err(|e| e.at("spend"))?;
+        let signers = match signature {
+            Some(Tree(signatures)) => signatures
+                .into_iter()
+                .map(|(PointKey(key), _)| key)
+                .collect(),
+            None => Vec::new(),
+        };
+        Ok(Self {
+            lock: note.lock,
+            assets: note.assets,
+            signers,
+            seeds: seeds
+                .into_iter()
+                .map(|Seed(NoteLock(lock), gift)| (lock, gift))
+                .collect(),
+            fee,
+        })
+    }
+}
+
+struct Note {
+    lock: Lock,
+    assets: u64,
+}
+
+// `[[version origin-page timelock] name lock source assets]`
+impl FromNoun for Note {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let (_, (_, (NoteLock(lock), (_, assets)))) =
+            <(Noun, (Noun, (NoteLock, (Noun, u64))))>::from_noun(noun).map_err(|e| e.at("note"))?;
+        Ok(Self { lock, assets })
+    }
+}
+
+/// The lock of each note of the wallet, by note name, as held in the wallet
+/// kernel's state.
+pub struct NoteLocks(pub Vec<(String, Lock)>);
+
+// the state `[%0 balance=(z-map nname nnote) ...]`, following `state-0` of the wallet
+// kernel at the nockchain revision pinned in Cargo.toml (61e581e1), which
+// `assets/wal.jam` is built from. Only the version and the balance are read, the
+// fields after it are skipped; this has to be checked again when the kernel changes.
+impl FromNoun for NoteLocks {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let (_, (Tree(balance), _)) = <(Noun, (Tree<(NoteName, Note)>, Noun))>::from_noun(noun)
+            .map_err(|e| e.at("balance"))?;
+        Ok(NoteLocks(
+            balance
+                .into_iter()
+                .map(|(NoteName(name), note)| (name, note.lock))
+                .collect(),
+        ))
+    }
+}
+
+struct Seed(NoteLock, u64);
+
+// `[output-source recipient=lock timelock-intent gift=coins parent-hash]`
+impl FromNoun for Seed {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let (_, (recipient, (_, (gift, _)))) =
+            <(Noun, (NoteLock, (Noun, (u64, Noun))))>::from_noun(noun)?;
+        Ok(Seed(recipient, gift))
+    }
+}
+
+struct NoteLock(Lock);
+
+// `[m=@ pubkeys=(z-set schnorr-pubkey)]`
+impl FromNoun for NoteLock {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let (threshold, Tree(keys)) = <(u64, Tree<PointKey>)>::from_noun(noun)?;
+        let keys = keys.into_iter().map(|PointKey(key)| key).collect();
+        Lock::multisig(threshold, keys)
+            .map(NoteLock)
+            .map_err(DecodeError::Invalid)
+    }
+}
+
+struct PointKey(Pubkey);
+
+// a curve point `[x=f6lt y=f6lt inf=?]`, each coordinate six belts, serialized like
+// the kernel's `ser-a-pt`: the belts of x and y and the flag as the 64-bit digits of
+// one atom, least significant first, which is then written big-endian in base58
+impl FromNoun for PointKey {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let (Belts(x), (Belts(y), inf)) = <(Belts<6>, (Belts<6>, u64))>::from_noun(noun)?;
+        let digits: Vec<u64> = x.into_iter().chain(y).chain([inf]).collect();
+        let key = Pubkey::parse(&base58(&digits)).map_err(DecodeError::Invalid)?;
+        Ok(PointKey(key))
+    }
+}
+
+struct NoteName(String);
+
+// `[first=hash last=hash ~]`, each hash five belts
+impl FromNoun for NoteName {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let (Belts(first), (Belts(last), ())) = <(Belts<5>, (Belts<5>, ()))>::from_noun(noun)?;
+        Ok(NoteName(format!("[{} {}]", hash(first), hash(last))))
+    }
+}
+
+// a tuple of `N` atoms, `[a b ... z]`
+struct Belts<const N: usize>([u64; N]);
+
+impl<const N: usize> FromNoun for Belts<N> {
+    fn from_noun(noun: &Noun) -> Result<Self, DecodeError> {
+        let mut belts = [0; N];
+        let mut rest = *noun;
+        for (i, belt) in belts.iter_mut().enumerate() {
+            let item = if i + 1 < N {
+                let cell = rest
+                    .as_cell()
+                    .map_err(|_| DecodeError::NotCell.at(format!("belt {}", i)))?;
+                rest = cell.tail();
+                cell.head()
+            } else {
+                rest
+            };
+            *belt = u64::from_noun(&item).map_err(|e| e.at(format!("belt {}", i)))?;
+        }
+        Ok(Belts(belts))
+    }
+}
+
+// a hash is five digits base p, least significant first, written in base58 as one atom
+fn hash(digits: [u64; 5]) -> String {
+    let mut limbs: Vec<u64> = Vec::new();
+    for digit in digits.iter().rev() {
+        let mut carry = *digit as u128;
+        for limb in limbs.iter_mut() {
+            let value = *limb as u128 * P as u128 + carry;
+            *limb = value as u64;
+            carry = value >> 64;
+        }
+        if carry > 0 {
+            limbs.push(carry as u64);
+        }
+    }
+    base58(&limbs)
+}
+
+// base58 of the atom with the given 64-bit limbs, least significant first
+fn base58(limbs: &[u64]) -> String {
+    let mut bytes: Vec<u8> = limbs.iter().flat_map(|limb| limb.to_le_bytes()).collect();
+    while bytes.last() == Some(&0) {
+        bytes.pop();
+    }
+    bytes.reverse();
+    bs58::encode(bytes).into_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use nockapp::AtomExt;
+    use nockvm::noun::{Atom, D, T};
+
+    use super::*;
+    use crate::manager::wallet::{NockchainTxStatus, TransactionEntry};
+
+    // base58 of the hashes `[1 2 3 4 5]`, `[6 7 8 9 10]`, `[11 12 13 14 15]` and
+    // `[16 17 18 19 20]`, and of the points with belts `1..=12` and `21..=32`
+    const FIRST: &str = "2V9arU36gvtaofWmNowewoj9u7gbNA2qsJZEQ3WPky5mQ";
+    const LAST: &str = "3yJAhw5CNrnB1fdWrjUSrDV8jJqQxtbqfEPSo1s7eaE3h";
+    const OTHER_FIRST: &str = "5TSkZQ7J4nfmDfkGLf1EkdF7ZVzEZdAqTADfBzDqYBNKz";
+    const OTHER_LAST: &str = "6wbLQs9PkiZMRfs1paY2f316Ph94AMjqF63saxaZRnWcH";
+    const OWN_KEY: &str = "2XBmFA5EdCJHZcjBDS7ZAncrkmfcgsnZu5emgX8aBwVW42JQ1GfwRNxy3wdab9z23FLHSxAiMpuTEv7BF2o7NoPNCVCLFs9ygm3xjXBNLjDQF9Rom1QzkQ2ppS6XjuznMz7J";
+    const OTHER_KEY: &str = "2XBmFA5EdCJzkcb2HnLEMQdj7L3hn2SsZrkshRxNtQzm88fAeKLsJAZ3FWS83CJGWRafbwpfGaU8RzhwnqZWVmqPmsCQejTCgxZrUuXLANaV3Ss4kYc7PgnXbPFtgoP9BVUx";
+
+    fn belts(slab: &mut NounSlab, values: impl Iterator<Item = u64>) -> Noun {
+        let items: Vec<Noun> = values.map(D).collect();
+        T(slab, &items)
+    }
+
+    // the name with the hashes `[start .. start+4]` and `[start+5 .. start+9]`
+    fn note_name(slab: &mut NounSlab, start: u64) -> Noun {
+        let first = belts(slab, start..start + 5);
+        let last = belts(slab, start + 5..start + 10);
+        T(slab, &[first, last, D(0)])
+    }
+
+    // a point that isn't the point at infinity, `inf` is `%.n`
+    fn point(slab: &mut NounSlab, start: u64) -> Noun {
+        let x = belts(slab, start..start + 6);
+        let y = belts(slab, start + 6..start + 12);
+        T(slab, &[x, y, D(1)])
+    }
+
+    fn lock(slab: &mut NounSlab, key: Noun) -> Noun {
+        let keys = T(slab, &[key, D(0), D(0)]);
+        T(slab, &[D(1), keys])
+    }
+
+    fn seed(slab: &mut NounSlab, recipient: Noun, gift: u64) -> Noun {
+        T(slab, &[D(0), recipient, D(0), D(gift), D(0)])
+    }
+
+    // a transaction spending one note of 160 nicks, signed by the points starting at
+    // each of `signers`, sending 100 to `OTHER_KEY`, 50 back to `OWN_KEY` and paying a
+    // fee of 10
+    fn transaction(slab: &mut NounSlab, signers: &[u64]) -> Noun {
+        let name = Atom::from_value(slab, "draft").unwrap().as_noun();
+        let note_name = note_name(slab, 1);
+        let own = point(slab, 1);
+        let own_lock = lock(slab, own);
+        let other = point(slab, 21);
+        let other_lock = lock(slab, other);
+
+        let header = T(slab, &[D(0), D(0), D(0)]);
+        let note = T(slab, &[header, note_name, own_lock, D(0), D(160)]);
+        let mut signatures = D(0);
+        for signer in signers {
+            let key = point(slab, *signer);
+            let signature = T(slab, &[key, D(8)]);
+            signatures = T(slab, &[signature, D(0), signatures]);
+        }
+        if !signers.is_empty() {
+            signatures = T(slab, &[D(0), signatures]);
+        }
+        let sent = seed(slab, other_lock, 100);
+        let change = seed(slab, own_lock, 50);
+        let change = T(slab, &[change, D(0), D(0)]);
+        let seeds = T(slab, &[sent, change, D(0)]);
+        let spend = T(slab, &[signatures, seeds, D(10)]);
+        let input = T(slab, &[note, spend]);
+        let entry = T(slab, &[note_name, input]);
+        let inputs = T(slab, &[entry, D(0), D(0)]);
+        T(slab, &[name, inputs])
+    }
+
+    fn meta(amount: u64) -> NockchainTxMeta {
+        NockchainTxMeta {
+            draft_id: "draft".to_string(),
+            transactions: vec![TransactionEntry {
+                recipient: Lock::single(Pubkey::parse(OTHER_KEY).unwrap()).to_string(),
+                amount: Amount::from_nicks(amount),
+            }],
+            fee: Amount::from_nicks(10),
+            created_at: String::new(),
+            signed_at: None,
+            broadcasted_at: None,
+            status: NockchainTxStatus::Draft,
+            inputs: vec![format!("[{} {}]", FIRST, LAST)],
+            broadcast_height: None,
+            confirmed_height: None,
+            signed_by: Vec::new(),
+            inputs_missing_at: None,
+        }
+    }
+
+    #[test]
+    fn note_names_decode_nested_hashes() {
+        let mut slab = NounSlab::new();
+        let name = note_name(&mut slab, 1);
+        let NoteName(name) = NoteName::from_noun(&name).unwrap();
+        assert_eq!(name, format!("[{} {}]", FIRST, LAST));
+
+        // the ten belts as one flat tuple are not a note name
+        let flat = belts(&mut slab, 1..=10);
+        assert!(NoteName::from_noun(&flat).is_err());
+        // nor is a name without its terminator
+        let first = belts(&mut slab, 1..=5);
+        let last = belts(&mut slab, 6..=10);
+        let unterminated = T(&mut slab, &[first, last]);
+        assert!(NoteName::from_noun(&unterminated).is_err());
+    }
+
+    #[test]
+    fn point_keys_decode_nested_coordinates() {
+        let mut slab = NounSlab::new();
+        let key = point(&mut slab, 1);
+        let PointKey(key) = PointKey::from_noun(&key).unwrap();
+        assert_eq!(key.to_string(), OWN_KEY);
+
+        let flat = belts(&mut slab, (1..=12).chain([1]));
+        assert!(PointKey::from_noun(&flat).is_err());
+        // a coordinate with a missing belt
+        let x = belts(&mut slab, 1..=5);
+        let y = belts(&mut slab, 7..=12);
+        let short = T(&mut slab, &[x, y, D(1)]);
+        assert!(PointKey::from_noun(&short).is_err());
+    }
+
+    fn decoded(signers: &[u64]) -> DraftContents {
+        let mut slab = NounSlab::new();
+        let transaction = transaction(&mut slab, signers);
+        slab.set_root(transaction);
+        decode(slab.jam().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn jammed_transactions_read_back() {
+        let mut slab = NounSlab::new();
+        let transaction = transaction(&mut slab, &[1]);
+        slab.set_root(transaction);
+        let path = std::env::temp_dir().join(format!("aeroe-inspect-{}.draft", std::process::id()));
+        fs::write(&path, slab.jam()).unwrap();
+        let contents = read(&path);
+        fs::remove_file(&path).unwrap();
+        let contents = contents.unwrap();
+
+        let own = Lock::single(Pubkey::parse(OWN_KEY).unwrap());
+        let other = Lock::single(Pubkey::parse(OTHER_KEY).unwrap());
+        assert_eq!(contents.name, "draft");
+        assert_eq!(contents.inputs.len(), 1);
+        assert_eq!(contents.inputs[0].name, format!("[{} {}]", FIRST, LAST));
+        assert_eq!(contents.inputs[0].value, Amount::from_nicks(160));
+        assert_eq!(contents.inputs[0].signed_by, [OWN_KEY]);
+        assert_eq!(contents.outputs.len(), 1);
+        assert_eq!(contents.outputs[0].lock, other.to_string());
+        assert_eq!(contents.outputs[0].amount, Amount::from_nicks(100));
+        assert_eq!(contents.lock, own.to_string());
+        assert_eq!(contents.change, Amount::from_nicks(50));
+        assert_eq!(contents.fee, Amount::from_nicks(10));
+
+        check(&meta(100), &contents).unwrap();
+        assert!(check(&meta(90), &contents).is_err());
+    }
+
+    #[test]
+    fn signatures_only_grow() {
+        let unsigned = decoded(&[]);
+        let one = decoded(&[1]);
+        let both = decoded(&[1, 21]);
+        assert!(unsigned.inputs[0].signed_by.is_empty());
+
+        assert_eq!(check_signed_over(&unsigned, &one).unwrap(), [OWN_KEY]);
+        let mut signers = check_signed_over(&one, &both).unwrap();
+        signers.sort();
+        let mut expected = vec![OWN_KEY.to_string(), OTHER_KEY.to_string()];
+        expected.sort();
+        assert_eq!(signers, expected);
+
+        // a signature dropped, none added, or the transaction changed
+        assert!(check_signed_over(&both, &one).is_err());
+        assert!(check_signed_over(&one, &decoded(&[21])).is_err());
+        assert!(check_signed_over(&one, &one).is_err());
+        let mut changed = both.clone();
+        changed.change = Amount::from_nicks(40);
+        changed.fee = Amount::from_nicks(20);
+        assert!(check_signed_over(&one, &changed).is_err());
+    }
+
+    // a wallet state holding a note locked to `OWN_KEY` alone and one locked to
+    // both keys, in the `state-0` layout `NoteLocks` reads
+    #[test]
+    fn wallet_state_gives_note_locks() {
+        let mut slab = NounSlab::new();
+        let own = point(&mut slab, 1);
+        let other = point(&mut slab, 21);
+        let header = T(&mut slab, &[D(0), D(0), D(0)]);
+
+        let single_name = note_name(&mut slab, 1);
+        let single_lock = lock(&mut slab, own);
+        let single = T(&mut slab, &[header, single_name, single_lock, D(0), D(160)]);
+        let single = T(&mut slab, &[single_name, single]);
+
+        let shared_name = note_name(&mut slab, 11);
+        let keys = T(&mut slab, &[other, D(0), D(0)]);
+        let keys = T(&mut slab, &[own, D(0), keys]);
+        let shared_lock = T(&mut slab, &[D(2), keys]);
+        let shared = T(&mut slab, &[header, shared_name, shared_lock, D(0), D(90)]);
+        let shared = T(&mut slab, &[shared_name, shared]);
+
+        let right = T(&mut slab, &[shared, D(0), D(0)]);
+        let balance = T(&mut slab, &[single, D(0), right]);
+        let state = T(&mut slab, &[D(0), balance, D(0), D(0)]);
+
+        let NoteLocks(locks) = NoteLocks::from_noun(&state).unwrap();
+        let own = Pubkey::parse(OWN_KEY).unwrap();
+        let other = Pubkey::parse(OTHER_KEY).unwrap();
+        assert_eq!(
+            locks,
+            vec![
+                (format!("[{} {}]", FIRST, LAST), Lock::single(own.clone())),
+                (
+                    format!("[{} {}]", OTHER_FIRST, OTHER_LAST),
+                    Lock::multisig(2, vec![other, own]).unwrap()
+                ),
+            ]
+        );
+
+        // a state whose second field isn't the balance map fails rather than
+        // reading the wrong notes
+        let wrong = T(&mut slab, &[D(0), D(5), D(0)]);
+        assert!(NoteLocks::from_noun(&wrong).is_err());
+    }
+}
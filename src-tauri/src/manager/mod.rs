@@ -1,10 +1,18 @@
+pub mod bundle;
+pub mod coin_select;
+pub mod draft;
+pub mod fee;
+pub mod history;
+pub mod inspect;
 pub mod nockchain_node;
 pub mod wallet;
 
 pub use nockchain_node::*;
 pub use wallet::*;
 
-use nockvm::noun::Noun;
+use nockapp::noun::slab::NounSlab;
+
+use crate::decode;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum NockchainPeek {
@@ -15,12 +23,13 @@ pub enum NockchainPeek {
 
 pub struct NockchainStatus {
     pub command: NockchainPeek,
-    pub noun: Noun,
+    /// the peek result as returned by the kernel, `[~ ~ value]`
+    pub slab: NounSlab,
 }
 
 impl NockchainStatus {
-    pub fn new(command: NockchainPeek, noun: Noun) -> Self {
-        Self { command, noun }
+    pub fn new(command: NockchainPeek, slab: NounSlab) -> Self {
+        Self { command, slab }
     }
     pub fn command(&self) -> &NockchainPeek {
         &self.command
@@ -29,12 +38,6 @@ impl NockchainStatus {
         if self.command != NockchainPeek::Height {
             return Err(format!("not a height command: {:?}", self.command()));
         }
-        let Ok(atom) = self.noun.as_atom() else {
-            return Err(format!("invalid noun, not an atom: {:?}", self.noun));
-        };
-        let Ok(height) = format!("{:?}", atom).parse::<u32>() else {
-            return Err("invalid noun, not a valid u32".to_string());
-        };
-        Ok(height)
+        decode::peek::<u32>(&self.slab).map_err(|e| format!("height: {}", e))
     }
 }
@@ -1,33 +1,59 @@
 use nockapp::noun::slab::NounSlab;
 use nockchain_wallet_lib::Commands;
-use nockvm::noun::Noun;
 
 use tokio::sync::mpsc::Sender;
 use tokio::sync::oneshot;
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
-use serde_json;
+
+use super::bundle;
+use super::coin_select::{Candidate, CoinSelector};
+use super::draft;
+use super::fee::{self, FeeEstimate, FeeSpec};
+use super::history::{self, History, HistoryPage};
+use super::inspect::{self, DraftContents, NoteLocks};
+use crate::amount::{self, Amount};
+use crate::decode::{self, FromNoun, Json};
+use crate::keycrypt::validate_wallet_name;
+use crate::pubkey::{recipient_lock, Lock, Pubkey};
+use crate::secret::{Secret, SecretString};
+use crate::timestamp::now_nanos;
 
 pub struct WalletCommand {
     pub command: Commands,
     pub response: oneshot::Sender<Result<Vec<NounSlab>, String>>,
 }
 
+/// Formats a wallet command for logging without the secrets some variants carry.
+pub fn redacted_command(command: &Commands) -> String {
+    match command {
+        Commands::GenMasterPrivkey { .. } => {
+            "GenMasterPrivkey { seedphrase: [REDACTED] }".to_string()
+        }
+        _ => format!("{:?}", command),
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TransactionEntry {
     pub recipient: String,
-    pub amount: u64,
+    pub amount: Amount,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum NockchainTxStatus {
     Draft,
+    /// a multisig draft with some but not all of the signatures it needs
+    Cosigning,
     Signed,
     Pending,
+    Confirmed,
+    Failed,
+    Expired,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -35,11 +61,25 @@ pub enum NockchainTxStatus {
 pub struct NockchainTxMeta {
     pub draft_id: String,
     pub transactions: Vec<TransactionEntry>,
-    pub fee: u64,
+    pub fee: Amount,
     pub created_at: String,
     pub signed_at: Option<String>,
     pub broadcasted_at: Option<String>,
     pub status: NockchainTxStatus,
+    /// notes spent by the transaction, as `[first last]`
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    #[serde(default)]
+    pub broadcast_height: Option<u32>,
+    #[serde(default)]
+    pub confirmed_height: Option<u32>,
+    /// pubkeys that signed a multisig draft so far
+    #[serde(default)]
+    pub signed_by: Vec<String>,
+    /// block height at which some input of the unsent draft was first missing from
+    /// the wallet's notes
+    #[serde(default)]
+    pub inputs_missing_at: Option<u32>,
 }
 
 #[derive(Debug)]
@@ -52,54 +92,181 @@ pub struct NockchainTx {
 pub struct Note {
     pub first: String,
     pub last: String,
-    pub assets: String,
+    #[serde(with = "amount::ud")]
+    pub assets: Amount,
+}
+
+impl Note {
+    /// Name of the note in the `[first last]` form used by spend commands.
+    pub fn name(&self) -> String {
+        format!("[{} {}]", self.first, self.last)
+    }
+}
+
+/// A note of the loaded wallet as shown for coin control.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteInfo {
+    pub name: String,
+    pub first: String,
+    pub last: String,
+    pub value: Amount,
 }
 
 #[derive(Debug)]
 pub struct Wallet {
     wallet_dir: PathBuf,
     draft_dir: PathBuf,
+    history_dir: PathBuf,
     command_tx: Sender<WalletCommand>,
     wallet_name: Option<String>,
     master_pubkey: Option<String>,
-    balance: Option<u64>,
+    watch_only: bool,
+    multisig: Option<Lock>,
+    balance: Option<Amount>,
     block_height: Option<u32>,
     last_sync: Option<std::time::Instant>,
     drafts: HashMap<String, NockchainTx>,
+    history: Option<History>,
 }
 
 impl Wallet {
     // creates new wallet manager
-    pub fn new(command_tx: Sender<WalletCommand>, wallet_dir: PathBuf, draft_dir: PathBuf) -> Self {
+    pub fn new(
+        command_tx: Sender<WalletCommand>,
+        wallet_dir: PathBuf,
+        draft_dir: PathBuf,
+        history_dir: PathBuf,
+    ) -> Self {
         Self {
             wallet_dir,
             draft_dir,
+            history_dir,
             command_tx,
             wallet_name: None,
             master_pubkey: None,
+            watch_only: false,
+            multisig: None,
             balance: None,
             block_height: None,
             last_sync: None,
             drafts: HashMap::new(),
+            history: None,
         }
     }
     pub async fn clear_state(&self) -> Result<(), String> {
         std::fs::remove_dir_all(self.wallet_dir.clone()).map_err(|e| e.to_string())?;
         Ok(())
     }
+    // forgets the active wallet and wipes its private key state from the kernel directory
+    pub async fn unload(&mut self) -> Result<(), String> {
+        self.wallet_name = None;
+        self.master_pubkey = None;
+        self.watch_only = false;
+        self.multisig = None;
+        self.balance = None;
+        self.last_sync = None;
+        self.drafts.clear();
+        self.history = None;
+        if self.wallet_dir.exists() {
+            self.clear_state().await?;
+        }
+        Ok(())
+    }
+    // moves the drafts and history of a wallet along with a rename in the vault
+    pub fn rename_wallet_files(&self, old_name: &str, new_name: &str) -> Result<(), String> {
+        // the old name may be a legacy one, the new one has to be valid
+        validate_wallet_name(new_name)?;
+        let dirs = [&self.draft_dir, &self.history_dir];
+        for dir in dirs {
+            if wallet_subdir(dir, new_name).exists() {
+                return Err(format!("{:?} already has files for {}", dir, new_name));
+            }
+        }
+        for (i, dir) in dirs.iter().enumerate() {
+            let old_dir = wallet_subdir(dir, old_name);
+            if !old_dir.exists() {
+                continue;
+            }
+            if let Err(e) = std::fs::rename(&old_dir, wallet_subdir(dir, new_name)) {
+                // put back what was already moved
+                for moved in &dirs[..i] {
+                    let _ = std::fs::rename(
+                        wallet_subdir(moved, new_name),
+                        wallet_subdir(moved, old_name),
+                    );
+                }
+                return Err(e.to_string());
+            }
+        }
+        Ok(())
+    }
+    // deletes the drafts and history of a wallet that was removed from the vault
+    pub fn remove_wallet_files(&self, wallet_name: &str) -> Result<(), String> {
+        for dir in [&self.draft_dir, &self.history_dir] {
+            let dir = wallet_subdir(dir, wallet_name);
+            if dir.exists() {
+                std::fs::remove_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
     pub fn get_active_wallet(&self) -> Option<String> {
         self.wallet_name.clone()
     }
     pub fn get_block_height(&self) -> Option<u32> {
         self.block_height
     }
+    pub fn is_watch_only(&self) -> bool {
+        self.watch_only
+    }
     pub async fn load(&mut self, wallet_name: String) -> Result<(), String> {
         self.wallet_name = Some(wallet_name);
+        self.watch_only = false;
+        self.multisig = None;
         let pubkey = self.peek_master_pubkey().await?;
         self.master_pubkey = Some(pubkey);
+        self.load_drafts()?;
+        self.load_history()?;
         self.update_state().await?;
         let balance = self.peek_balance().await?;
         self.balance = Some(balance);
+        self.sync_history().await?;
+        Ok(())
+    }
+    // loads a wallet that only has a master pubkey, balances and notes are peeked by that pubkey
+    // and no private key is generated in the kernel
+    pub async fn load_watch_only(
+        &mut self,
+        wallet_name: String,
+        master_pubkey: String,
+    ) -> Result<(), String> {
+        self.wallet_name = Some(wallet_name);
+        self.watch_only = true;
+        self.multisig = None;
+        self.master_pubkey = Some(master_pubkey);
+        self.load_drafts()?;
+        self.load_history()?;
+        self.update_state().await?;
+        let balance = self.peek_balance().await?;
+        self.balance = Some(balance);
+        self.sync_history().await?;
+        Ok(())
+    }
+    // loads a wallet that tracks the notes locked to a key set, it has no master pubkey
+    // and its drafts are signed by the wallets holding the keys with `cosign_tx`, here
+    // or on other installs that pass the draft along as a bundle
+    pub async fn load_multisig(&mut self, wallet_name: String, lock: Lock) -> Result<(), String> {
+        self.wallet_name = Some(wallet_name);
+        self.watch_only = false;
+        self.multisig = Some(lock);
+        self.master_pubkey = None;
+        self.load_drafts()?;
+        self.load_history()?;
+        self.update_state().await?;
+        let balance = self.peek_balance().await?;
+        self.balance = Some(balance);
+        self.sync_history().await?;
         Ok(())
     }
     pub async fn update(&mut self, new_height: u32) -> Result<(), String> {
@@ -128,10 +295,10 @@ impl Wallet {
         }
         self.update_state().await?;
         self.balance = Some(self.peek_balance().await?);
-        // update history
+        self.sync_history().await?;
         Ok(())
     }
-    pub async fn get_balance(&self) -> Result<u64, String> {
+    pub async fn get_balance(&self) -> Result<Amount, String> {
         let Some(balance) = self.balance else {
             return Err("balance is not set".to_string());
         };
@@ -146,90 +313,82 @@ impl Wallet {
     pub async fn create_tx(
         &mut self,
         transactions: Vec<TransactionEntry>,
-        fee: u64,
+        fee: FeeSpec,
+        selector: &dyn CoinSelector,
+        allow_self_send: bool,
     ) -> Result<NockchainTxMeta, String> {
         // if amount of transactions is 0, return error
         if transactions.is_empty() {
             return Err("no transactions".to_string());
         }
+        let locks = self.recipient_locks(&transactions, allow_self_send).await?;
         // if fee is 0, return error
-        if fee == 0 {
+        if fee == FeeSpec::Fixed(Amount::ZERO) {
             return Err("fee is 0".to_string());
         }
-        // if fee is greater than balance, return error
         let balance = self.get_balance().await?;
-        let total_amount = transactions.iter().map(|t| t.amount).sum::<u64>();
-
-        if (fee + total_amount) > balance {
-            return Err("spending amount is greater than balance".to_string());
-        }
+        let total_amount = Self::total_amount(&transactions)?;
         // list notes
         let notes = self.peek_notes().await?;
 
-        // find the lowest number of notes to complete the transaction
-        let required_amount = total_amount + fee;
-        let mut selected_notes = Vec::new();
-        let mut selected_amount = 0u64;
-
-        // Convert notes to (value, note) pairs and sort by value descending for greedy selection
-        let mut note_values: Vec<(u64, &Note)> = notes
-            .iter()
-            .filter_map(|note| {
-                note.assets
-                    .replace(".", "")
-                    .parse::<u64>()
-                    .ok()
-                    .map(|value| (value, note))
-            })
-            .collect();
-        note_values.sort_by(|a, b| b.0.cmp(&a.0)); // Sort descending by value
-
-        // Greedy selection: pick largest notes first until we have enough
-        for (value, note) in note_values {
-            if selected_amount >= required_amount {
-                break;
+        // select the notes to spend with the requested strategy or the user's choice,
+        // working out the fee on the way if it is automatic
+        let candidates = self.candidates(&notes);
+        let (fee, selected) = match fee {
+            FeeSpec::Fixed(fee) => {
+                let required_amount = total_amount
+                    .checked_add(fee)
+                    .ok_or("amount plus fee overflows".to_string())?;
+                (fee, selector.select(&candidates, required_amount.nicks())?)
             }
-            selected_notes.push(note);
-            selected_amount += value;
-        }
-
-        // Check if we have enough funds in available notes
-        if selected_amount < required_amount {
-            return Err("insufficient funds in available notes".to_string());
+            FeeSpec::Auto => fee::settle(
+                &candidates,
+                selector,
+                total_amount,
+                transactions.len(),
+                fee::NORMAL_RATE,
+            )?,
+        };
+        tracing::info!(
+            "selected {} of {} notes, fee {} nicks",
+            selected.len(),
+            candidates.len(),
+            fee.nicks()
+        );
+        // if fee is greater than balance, return error
+        let Some(spent) = total_amount.checked_add(fee) else {
+            return Err("amount plus fee overflows".to_string());
+        };
+        if spent > balance {
+            return Err("spending amount is greater than balance".to_string());
         }
 
         // construct simple-spend
-        let note_names = selected_notes
+        let inputs = selected
             .iter()
-            .map(|note| format!("[{} {}]", note.first, note.last))
-            .collect::<Vec<String>>()
-            .join(",");
-        let recipients = transactions
+            .map(|i| candidates[*i].note.name())
+            .collect::<Vec<String>>();
+        let note_names = inputs.join(",");
+        let recipients = locks
             .iter()
-            .map(|tx| format!("[1 {}]", tx.recipient))
+            .map(Lock::to_string)
             .collect::<Vec<String>>()
             .join(",");
         let gifts = transactions
             .iter()
-            .map(|tx| tx.amount.to_string())
+            .map(|tx| tx.amount.nicks().to_string())
             .collect::<Vec<String>>()
             .join(",");
 
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
+        let now = now_nanos();
 
         let draft_name = Self::generate_draft_name(&now);
 
         // create draft directory
-        let wallet_draft_dir = match self.wallet_name.clone() {
-            Some(wallet_name) => self.draft_dir.join(wallet_name),
-            None => return Err("wallet name is not set".to_string()),
-        };
+        let wallet_draft_dir = self.wallet_draft_dir()?;
         std::fs::create_dir_all(&wallet_draft_dir).map_err(|e| e.to_string())?;
 
-        let draft_file_path = wallet_draft_dir.join(format!("{}.draft", draft_name.clone()));
+        let draft_file_path = draft::draft_path(&wallet_draft_dir, &draft_name);
         let file_path = draft_file_path
             .to_str()
             .ok_or("draft file path contains invalid UTF-8".to_string())?
@@ -240,7 +399,7 @@ impl Wallet {
                 names: note_names,
                 recipients,
                 gifts,
-                fee,
+                fee: fee.nicks(),
                 file_path: file_path.clone(),
             })
             .await?;
@@ -251,11 +410,17 @@ impl Wallet {
             draft_id,
             transactions,
             fee,
-            created_at: now.to_string(),
+            created_at: now,
             signed_at: None,
             broadcasted_at: None,
             status: NockchainTxStatus::Draft,
+            inputs,
+            broadcast_height: None,
+            confirmed_height: None,
+            signed_by: Vec::new(),
+            inputs_missing_at: None,
         };
+        draft::write_meta(&wallet_draft_dir, &draft_meta)?;
         self.drafts.insert(
             draft_name.clone(),
             NockchainTx {
@@ -265,16 +430,40 @@ impl Wallet {
         );
         Ok(draft_meta)
     }
+    pub async fn estimate_fee(
+        &self,
+        transactions: &[TransactionEntry],
+        selector: &dyn CoinSelector,
+    ) -> Result<FeeEstimate, String> {
+        if transactions.is_empty() {
+            return Err("no transactions".to_string());
+        }
+        let total_amount = Self::total_amount(transactions)?;
+        let notes = self.peek_notes().await?;
+        let candidates = self.candidates(&notes);
+        fee::estimate(&candidates, selector, total_amount, transactions.len())
+    }
     pub async fn sign_tx(&mut self, draft_id: String) -> Result<NockchainTxMeta, String> {
-        // First, get the file path and check if draft exists
+        if self.watch_only {
+            return Err("wallet is watch-only, it cannot sign transactions".to_string());
+        }
+        if self.multisig.is_some() {
+            return Err("multisig drafts are signed by each cosigner with cosign_tx".to_string());
+        }
+        // First, get the file path and check the draft is the transaction that was created
         let file_path = {
             let Some(draft) = self.drafts.get(&draft_id) else {
                 return Err("draft not found".to_string());
             };
+            check_draft(draft)?;
             draft.location.clone()
         };
 
-        let signed_file_path = file_path.replace(".draft", ".signed");
+        let wallet_draft_dir = self.wallet_draft_dir()?;
+        let signed_file_path = draft::signed_path(&wallet_draft_dir, &draft_id)
+            .to_str()
+            .ok_or("draft file path contains invalid UTF-8".to_string())?
+            .to_string();
         // send command to sign the draft
         let _ = self
             .send_command(Commands::SignAeroeTx {
@@ -290,13 +479,55 @@ impl Wallet {
         };
         draft.metadata.status = NockchainTxStatus::Signed;
         draft.location = signed_file_path;
-        draft.metadata.signed_at = Some(
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_nanos()
-                .to_string(),
-        );
+        draft.metadata.signed_at = Some(now_nanos());
+        draft::write_meta(&wallet_draft_dir, &draft.metadata)?;
+        Ok(draft.metadata.clone())
+    }
+    /// Adds the signature of a cosigner to a draft of the loaded multisig wallet. The
+    /// cosigner's keys are put into the kernel for the signature and wiped right after.
+    /// Each signature is made over the file of the previous one, so the signatures add
+    /// up in the draft and the last file holds all of them.
+    pub async fn cosign_tx(
+        &mut self,
+        draft_id: String,
+        cosigner_seedphrase: &SecretString,
+    ) -> Result<NockchainTxMeta, String> {
+        let Some(lock) = self.multisig.clone() else {
+            return Err("wallet is not a multisig wallet".to_string());
+        };
+        let Some(draft) = self.drafts.get(&draft_id) else {
+            return Err("draft not found".to_string());
+        };
+        if !matches!(
+            draft.metadata.status,
+            NockchainTxStatus::Draft | NockchainTxStatus::Cosigning
+        ) {
+            return Err("draft already has all of its signatures".to_string());
+        }
+        check_draft(draft)?;
+        let draft_location = draft.location.clone();
+
+        self.gen_master_privkey(cosigner_seedphrase).await?;
+        let signed = self
+            .sign_as_cosigner(&lock, &draft_id, draft_location)
+            .await;
+        // drop the cosigner's private key whether or not signing worked
+        self.clear_state().await?;
+        self.update_state().await?;
+        let (cosigner, signed_file_path) = signed?;
+
+        let Some(draft) = self.drafts.get_mut(&draft_id) else {
+            return Err("draft not found".to_string());
+        };
+        draft.metadata.signed_by.push(cosigner.to_string());
+        draft.location = signed_file_path;
+        if draft.metadata.signed_by.len() as u64 >= lock.threshold() {
+            draft.metadata.status = NockchainTxStatus::Signed;
+            draft.metadata.signed_at = Some(now_nanos());
+        } else {
+            draft.metadata.status = NockchainTxStatus::Cosigning;
+        }
+        draft::write_meta(&self.wallet_draft_dir()?, &draft.metadata)?;
         Ok(draft.metadata.clone())
     }
     pub async fn send_tx(&mut self, draft_id: String) -> Result<NockchainTxMeta, String> {
@@ -305,6 +536,22 @@ impl Wallet {
             let Some(draft) = self.drafts.get(&draft_id) else {
                 return Err("draft not found".to_string());
             };
+            // a watch-only wallet sends drafts signed on the install that holds the seed
+            if self.watch_only && !matches!(draft.metadata.status, NockchainTxStatus::Signed) {
+                return Err(
+                    "wallet is watch-only, import the draft signed on another install first"
+                        .to_string(),
+                );
+            }
+            if let Some(lock) = &self.multisig {
+                if !matches!(draft.metadata.status, NockchainTxStatus::Signed) {
+                    return Err(format!(
+                        "draft has {} of the {} signatures it needs",
+                        draft.metadata.signed_by.len(),
+                        lock.threshold()
+                    ));
+                }
+            }
             draft.location.clone()
         };
         
@@ -319,15 +566,126 @@ impl Wallet {
             return Err("draft not found".to_string());
         };
         draft.metadata.status = NockchainTxStatus::Pending;
-        draft.metadata.broadcasted_at = Some(
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_nanos()
-                .to_string(),
+        draft.metadata.broadcast_height = self.block_height;
+        draft.metadata.broadcasted_at = Some(now_nanos());
+        let metadata = draft.metadata.clone();
+        draft::write_meta(&self.wallet_draft_dir()?, &metadata)?;
+        if let Some(history) = self.history.as_mut() {
+            history.record_sent(&metadata)?;
+        }
+        Ok(metadata)
+    }
+    /// Discards a draft that wasn't broadcast, or whose transaction failed or expired,
+    /// deleting its files and releasing the notes it reserved.
+    pub fn cancel_tx(&mut self, draft_id: &str) -> Result<(), String> {
+        let Some(draft) = self.drafts.get(draft_id) else {
+            return Err("draft not found".to_string());
+        };
+        if matches!(draft.metadata.status, NockchainTxStatus::Pending) {
+            return Err("transaction was already broadcast, it can't be cancelled".to_string());
+        }
+        draft::remove(&self.wallet_draft_dir()?, draft_id)?;
+        self.drafts.remove(draft_id);
+        Ok(())
+    }
+    /// Decodes the transaction file of a draft, to show what signing it would spend and pay.
+    pub fn inspect_draft(&self, draft_id: &str) -> Result<DraftContents, String> {
+        let Some(draft) = self.drafts.get(draft_id) else {
+            return Err("draft not found".to_string());
+        };
+        inspect::read(Path::new(&draft.location))
+    }
+    /// Writes a draft to a bundle file at `path`, to be signed on another install.
+    pub fn export_draft(&self, draft_id: &str, path: &Path) -> Result<(), String> {
+        let Some(draft) = self.drafts.get(draft_id) else {
+            return Err("draft not found".to_string());
+        };
+        bundle::write(&self.wallet_draft_dir()?, &draft.metadata, path)
+    }
+    /// Adds the draft of a bundle made on another install, so it can be signed here.
+    pub fn import_draft(&mut self, path: &Path) -> Result<NockchainTxMeta, String> {
+        let bundle = bundle::read(path)?;
+        if self.drafts.contains_key(&bundle.meta.draft_id) {
+            return Err(format!("draft {} already exists", bundle.meta.draft_id));
+        }
+        let wallet_draft_dir = self.wallet_draft_dir()?;
+        let metadata = bundle::import_draft(&wallet_draft_dir, bundle)?;
+        // a multisig draft may come with the signatures made so far
+        let location = if metadata.signed_by.is_empty() {
+            draft::draft_path(&wallet_draft_dir, &metadata.draft_id)
+        } else {
+            draft::signed_path(&wallet_draft_dir, &metadata.draft_id)
+        };
+        let location = location
+            .to_str()
+            .ok_or("draft file path contains invalid UTF-8".to_string())?
+            .to_string();
+        self.drafts.insert(
+            metadata.draft_id.clone(),
+            NockchainTx {
+                metadata: metadata.clone(),
+                location,
+            },
         );
+        Ok(metadata)
+    }
+    /// Takes back a draft of this wallet that was exported and signed on another install.
+    /// For a multisig wallet the signatures of cosigners on other installs are merged
+    /// in, as long as the file holds every signature the draft has here and more.
+    pub fn import_signed(&mut self, path: &Path) -> Result<NockchainTxMeta, String> {
+        let bundle = bundle::read(path)?;
+        let wallet_draft_dir = self.wallet_draft_dir()?;
+        let Some(draft) = self.drafts.get_mut(&bundle.meta.draft_id) else {
+            return Err(format!(
+                "draft {} not found, it must be exported from this wallet first",
+                bundle.meta.draft_id
+            ));
+        };
+        draft.metadata = match &self.multisig {
+            Some(lock) => {
+                if !matches!(
+                    draft.metadata.status,
+                    NockchainTxStatus::Draft | NockchainTxStatus::Cosigning
+                ) {
+                    return Err("draft already has all of its signatures".to_string());
+                }
+                let current = Path::new(&draft.location);
+                bundle::import_cosigned(&wallet_draft_dir, &draft.metadata, current, lock, bundle)?
+            }
+            None => {
+                if !matches!(draft.metadata.status, NockchainTxStatus::Draft) {
+                    return Err("draft is already signed".to_string());
+                }
+                bundle::import_signed(&wallet_draft_dir, &draft.metadata, bundle)?
+            }
+        };
+        draft.location = draft::signed_path(&wallet_draft_dir, &draft.metadata.draft_id)
+            .to_str()
+            .ok_or("draft file path contains invalid UTF-8".to_string())?
+            .to_string();
         Ok(draft.metadata.clone())
     }
+    pub async fn list_notes(&self) -> Result<Vec<NoteInfo>, String> {
+        if self.wallet_name.is_none() {
+            return Err("wallet is not loaded".to_string());
+        }
+        let notes = self.peek_notes().await?;
+        Ok(notes
+            .into_iter()
+            .map(|note| NoteInfo {
+                name: note.name(),
+                first: note.first,
+                last: note.last,
+                value: note.assets,
+            })
+            .collect())
+    }
+    pub fn tx_history(&self, offset: usize, limit: usize) -> Result<HistoryPage, String> {
+        let Some(history) = &self.history else {
+            return Err("wallet is not loaded".to_string());
+        };
+        Ok(history.page(offset, limit))
+    }
     pub async fn list_unsent_txs(&self) -> Result<HashMap<String, NockchainTxMeta>, String> {
         // self.drafts but only the key and metadata
         let unsent_txs = self
@@ -340,82 +698,76 @@ impl Wallet {
     //
     // peeks
     //
-    pub async fn peek_seedphrase(&self) -> Result<Vec<String>, String> {
+    pub async fn peek_seedphrase(&self) -> Result<Secret<Vec<String>>, String> {
         let result = self.send_command(Commands::PeekSeedphrase).await?;
-        let phrase = Self::clean_peek_noun(result)?;
-        let phrase_atom = phrase
-            .as_atom()
-            .map_err(|_| "seedphrase: phrase is not an atom".to_string())?;
-        let phrase_bytes = phrase_atom.as_ne_bytes();
-        let actual_phrase_str = std::str::from_utf8(phrase_bytes)
-            .map_err(|e| format!("keygen: phrase atom bytes are not valid UTF-8: {}", e))?
-            .to_string();
+        let phrase = SecretString::new(Self::decode_peek("seedphrase", result)?);
 
         // split the phrase at the spaces and return a vector of strings
-        let words = actual_phrase_str
+        let words = phrase
+            .expose()
             .split_whitespace()
-            .map(|s| s.to_string())
-            .collect::<Vec<String>>();
-        let cleaned_words = words
-            .iter()
-            .map(|s| s.replace("\u{0000}", ""))
+            .map(str::to_string)
             .collect::<Vec<String>>();
-        Ok(cleaned_words)
+        Ok(Secret::new(words))
     }
     async fn peek_master_pubkey(&self) -> Result<String, String> {
         if self.wallet_name.is_none() {
             return Err("wallet is not loaded".to_string());
         }
         let result = self.send_command(Commands::PeekMasterPubkey).await?;
-        let pubkey = Self::clean_peek_noun(result)?;
-        let pubkey_atom = pubkey
-            .as_atom()
-            .map_err(|_| "pubkey: pubkey is not an atom".to_string())?;
-        let pubkey_bytes = pubkey_atom.as_ne_bytes();
-        let actual_pubkey_str = std::str::from_utf8(pubkey_bytes)
-            .map_err(|e| format!("pubkey: pubkey atom bytes are not valid UTF-8: {}", e))?
-            .to_string();
-
-        Ok(actual_pubkey_str.replace("\u{0000}", ""))
+        Self::decode_peek("pubkey", result)
     }
-    async fn peek_balance(&self) -> Result<u64, String> {
+    async fn peek_balance(&self) -> Result<Amount, String> {
         if self.wallet_name.is_none() {
             return Err("wallet is not loaded".to_string());
         }
+        if self.multisig.is_some() {
+            // the kernel only sums the notes of a single pubkey
+            let notes = self.peek_notes().await?;
+            return Amount::checked_sum(notes.iter().map(|note| note.assets))
+                .ok_or("balance overflows".to_string());
+        }
         let result = self.send_command(Commands::PeekBalance{
             pubkey: self.get_master_pubkey().await?,
         }).await?;
-        let noun = Self::clean_peek_noun(result)?;
-        let atom = noun
-            .as_atom()
-            .map_err(|_| "balance: noun is not an atom".to_string())?;
-        let balance = format!("{:?}", atom)
-            .parse::<u64>()
-            .map_err(|e| format!("balance: atom is not a valid i64: {}", e))?;
+        let balance = Self::decode_peek("balance", result)?;
 
-        Ok(balance)
+        Ok(Amount::from_nicks(balance))
     }
     async fn peek_notes(&self) -> Result<Vec<Note>, String> {
-        let result = self.send_command(Commands::PeekNotes {
-            pubkey: self.get_master_pubkey().await?,
-        }).await?;
-        let notes = Self::clean_peek_noun(result)?;
-        let notes_atom = notes
-            .as_atom()
-            .map_err(|_| "notes: notes is not an atom".to_string())?;
-        let notes_bytes = notes_atom.as_ne_bytes();
-
-        // Trim null bytes and other trailing characters
-        let trimmed_bytes = notes_bytes
-            .iter()
-            .position(|&b| b == 0)
-            .map(|pos| &notes_bytes[..pos])
-            .unwrap_or(notes_bytes);
-
-        let notes_vec: Vec<Note> = serde_json::from_slice(trimmed_bytes)
-            .map_err(|e| format!("notes: failed to deserialize notes from bytes: {}", e))?;
+        let Some(lock) = &self.multisig else {
+            return self.peek_notes_by(self.get_master_pubkey().await?).await;
+        };
+        // a note locked to the key set shows up under each of its keys
+        let mut notes: Option<Vec<Note>> = None;
+        for key in lock.keys() {
+            let found = self.peek_notes_by(key.to_string()).await?;
+            notes = Some(match notes {
+                None => found,
+                Some(notes) => notes
+                    .into_iter()
+                    .filter(|note| found.iter().any(|other| other.name() == note.name()))
+                    .collect(),
+            });
+        }
+        // so do notes under another threshold or a larger key set, which the
+        // wallet can't spend, only the notes locked to exactly this lock are kept
+        let result = self.send_command(Commands::PeekState).await?;
+        let NoteLocks(locks) = Self::decode_peek("state", result)?;
+        let owned: HashSet<String> = locks
+            .into_iter()
+            .filter(|(_, locked)| locked == lock)
+            .map(|(name, _)| name)
+            .collect();
+        let mut notes = notes.unwrap_or_default();
+        notes.retain(|note| owned.contains(&note.name()));
+        Ok(notes)
+    }
+    async fn peek_notes_by(&self, pubkey: String) -> Result<Vec<Note>, String> {
+        let result = self.send_command(Commands::PeekNotes { pubkey }).await?;
+        let Json(notes) = Self::decode_peek::<Json<Vec<Note>>>("notes", result)?;
 
-        Ok(notes_vec)
+        Ok(notes)
     }
     //
     // pokes
@@ -424,9 +776,12 @@ impl Wallet {
         let _ = self.send_command(Commands::Keygen).await?;
         Ok(())
     }
-    pub async fn gen_master_privkey(&self, seedphrase: String) -> Result<(), String> {
+    pub async fn gen_master_privkey(&self, seedphrase: &SecretString) -> Result<(), String> {
+        // the command takes ownership of a plain copy, which is dropped once the poke is built
         let _ = self
-            .send_command(Commands::GenMasterPrivkey { seedphrase })
+            .send_command(Commands::GenMasterPrivkey {
+                seedphrase: seedphrase.expose().clone(),
+            })
             .await?;
         Ok(())
     }
@@ -441,9 +796,192 @@ impl Wallet {
     //
     // Helpers
     //
+    fn load_history(&mut self) -> Result<(), String> {
+        let Some(wallet_name) = &self.wallet_name else {
+            return Err("wallet name is not set".to_string());
+        };
+        let wallet_history_dir = wallet_subdir(&self.history_dir, wallet_name);
+        self.history = Some(History::load(&wallet_history_dir)?);
+        Ok(())
+    }
+    // records received notes and resolves broadcast transactions against the current notes
+    async fn sync_history(&mut self) -> Result<(), String> {
+        let notes = self.peek_notes().await?;
+        let wallet_draft_dir = self.wallet_draft_dir()?;
+        let Some(history) = self.history.as_mut() else {
+            return Err("history is not loaded".to_string());
+        };
+        history.record_notes(&notes, self.block_height)?;
+        let Some(height) = self.block_height else {
+            return Ok(());
+        };
+
+        let current = notes.iter().map(Note::name).collect::<HashSet<String>>();
+        let mut confirmed = Vec::new();
+        for (draft_id, draft) in self.drafts.iter_mut() {
+            // an unsent draft whose notes were spent elsewhere can't be sent anymore.
+            // A single sync may miss notes, so the draft only expires once they are
+            // still missing at a later block
+            if draft::inputs_spent(&draft.metadata, &current) {
+                match draft.metadata.inputs_missing_at {
+                    Some(missing_at) if height > missing_at => {
+                        tracing::info!(
+                            "draft {} expired, its notes were spent elsewhere",
+                            draft_id
+                        );
+                        draft.metadata.status = NockchainTxStatus::Expired;
+                    }
+                    Some(_) => continue,
+                    None => draft.metadata.inputs_missing_at = Some(height),
+                }
+                draft::write_meta(&wallet_draft_dir, &draft.metadata)?;
+                continue;
+            }
+            if draft::is_unsent(&draft.metadata) && draft.metadata.inputs_missing_at.is_some() {
+                draft.metadata.inputs_missing_at = None;
+                draft::write_meta(&wallet_draft_dir, &draft.metadata)?;
+            }
+            // sent before the block height was known, count from the first sync instead
+            if matches!(draft.metadata.status, NockchainTxStatus::Pending)
+                && draft.metadata.broadcast_height.is_none()
+            {
+                draft.metadata.broadcast_height = Some(height);
+                history.record_sent(&draft.metadata)?;
+                draft::write_meta(&wallet_draft_dir, &draft.metadata)?;
+                continue;
+            }
+            let Some(status) = history::resolve(&draft.metadata, &notes, height) else {
+                continue;
+            };
+            tracing::info!("transaction {} is now {:?}", draft_id, status);
+            if matches!(status, NockchainTxStatus::Confirmed) {
+                draft.metadata.confirmed_height = Some(height);
+                confirmed.push(draft_id.clone());
+            }
+            draft.metadata.status = status;
+            history.record_sent(&draft.metadata)?;
+            draft::write_meta(&wallet_draft_dir, &draft.metadata)?;
+        }
+        // confirmed transactions only live on in the history
+        for draft_id in confirmed {
+            self.drafts.remove(&draft_id);
+            draft::remove(&wallet_draft_dir, &draft_id)?;
+        }
+        Ok(())
+    }
+    // the notes that can fund a transaction, with their values and age; notes
+    // reserved by other drafts are left out
+    fn candidates<'a>(&self, notes: &'a [Note]) -> Vec<Candidate<'a>> {
+        let received_order = match &self.history {
+            Some(history) => history.received_order(),
+            None => HashMap::new(),
+        };
+        let reserved = self.reserved_notes();
+        notes
+            .iter()
+            .filter(|note| !reserved.contains(&note.name()))
+            .map(|note| Candidate {
+                note,
+                value: note.assets.nicks(),
+                first_seen: received_order.get(&note.name()).copied(),
+            })
+            .collect()
+    }
+    // the input notes of the drafts that still hold them
+    fn reserved_notes(&self) -> HashSet<String> {
+        self.drafts
+            .values()
+            .filter(|draft| draft::reserves_inputs(&draft.metadata))
+            .flat_map(|draft| draft.metadata.inputs.iter().cloned())
+            .collect()
+    }
+    // signs a multisig draft with the keys currently in the kernel, returning the
+    // signer's pubkey and the new signed file
+    async fn sign_as_cosigner(
+        &self,
+        lock: &Lock,
+        draft_id: &str,
+        draft_location: String,
+    ) -> Result<(Pubkey, String), String> {
+        let cosigner = Pubkey::parse(&self.peek_master_pubkey().await?)?;
+        if !lock.keys().contains(&cosigner) {
+            return Err(format!("{} is not one of the multisig keys", cosigner));
+        }
+        let Some(draft) = self.drafts.get(draft_id) else {
+            return Err("draft not found".to_string());
+        };
+        if draft.metadata.signed_by.contains(&cosigner.to_string()) {
+            return Err(format!("draft is already signed by {}", cosigner));
+        }
+
+        let wallet_draft_dir = self.wallet_draft_dir()?;
+        // sign into a temporary file, the current file may be the signed one
+        let cosign_path = wallet_draft_dir.join(format!("{}.cosign", draft_id));
+        let signed_path = draft::signed_path(&wallet_draft_dir, draft_id);
+        let _ = self
+            .send_command(Commands::SignAeroeTx {
+                draft: draft_location,
+                index: None,
+                file_path: cosign_path
+                    .to_str()
+                    .ok_or("draft file path contains invalid UTF-8".to_string())?
+                    .to_string(),
+            })
+            .await?;
+        std::fs::rename(&cosign_path, &signed_path)
+            .map_err(|e| format!("failed to store the cosigned draft: {}", e))?;
+        let signed_file_path = signed_path
+            .to_str()
+            .ok_or("draft file path contains invalid UTF-8".to_string())?
+            .to_string();
+        Ok((cosigner, signed_file_path))
+    }
+    // validates the recipients and builds the lock of each output
+    async fn recipient_locks(
+        &self,
+        transactions: &[TransactionEntry],
+        allow_self_send: bool,
+    ) -> Result<Vec<Lock>, String> {
+        let own_lock = match &self.multisig {
+            Some(lock) => Some(lock.clone()),
+            None => self
+                .master_pubkey
+                .as_deref()
+                .and_then(|pubkey| Pubkey::parse(pubkey).ok())
+                .map(Lock::single),
+        };
+        transactions
+            .iter()
+            .map(|tx| recipient_lock(&tx.recipient, own_lock.as_ref(), allow_self_send))
+            .collect()
+    }
+    fn total_amount(transactions: &[TransactionEntry]) -> Result<Amount, String> {
+        Amount::checked_sum(transactions.iter().map(|tx| tx.amount))
+            .ok_or("total amount overflows".to_string())
+    }
+    fn wallet_draft_dir(&self) -> Result<PathBuf, String> {
+        match &self.wallet_name {
+            Some(wallet_name) => Ok(wallet_subdir(&self.draft_dir, wallet_name)),
+            None => Err("wallet name is not set".to_string()),
+        }
+    }
+    // rebuilds `drafts` from the files of the loaded wallet
+    fn load_drafts(&mut self) -> Result<(), String> {
+        let mut drafts = HashMap::new();
+        for (draft_id, (metadata, location)) in draft::read_drafts(&self.wallet_draft_dir()?)? {
+            let location = location
+                .to_str()
+                .ok_or("draft file path contains invalid UTF-8".to_string())?
+                .to_string();
+            drafts.insert(draft_id, NockchainTx { metadata, location });
+        }
+        tracing::info!("loaded {} drafts", drafts.len());
+        self.drafts = drafts;
+        Ok(())
+    }
     async fn send_command(&self, command: Commands) -> Result<Vec<NounSlab>, String> {
         let (resp_tx, resp_rx) = oneshot::channel();
-        tracing::info!("sending command: {:?}", command);
+        tracing::info!("sending command: {}", redacted_command(&command));
         self.command_tx
             .send(WalletCommand {
                 command,
@@ -453,19 +991,14 @@ impl Wallet {
             .map_err(|_| "wallet thread gone".to_string())?;
         resp_rx.await.map_err(|_| "no reply".to_string())?
     }
-    fn clean_peek_noun(result: Vec<NounSlab>) -> Result<Noun, String> {
-        let effect = unsafe { result[0].root() };
-        let unit_noun = effect
-            .as_cell()
-            .map_err(|_| "invalid noun".to_string())?
-            .tail();
-        let noun = unit_noun
-            .as_cell()
-            .map_err(|_| "invalid noun".to_string())?
-            .tail();
-        Ok(noun)
-    }
-    fn generate_draft_name(seed: &u128) -> String {
+    // decodes the first effect of a peek command, naming `what` in errors
+    fn decode_peek<T: FromNoun>(what: &str, result: Vec<NounSlab>) -> Result<T, String> {
+        let Some(slab) = result.first() else {
+            return Err(format!("{}: peek returned no effects", what));
+        };
+        decode::peek(slab).map_err(|e| format!("{}: {}", what, e))
+    }
+    fn generate_draft_name(seed: &str) -> String {
         let mut hasher = std::collections::hash_map::DefaultHasher::new();
         std::hash::Hash::hash(seed, &mut hasher);
         std::hash::Hash::hash(&std::thread::current().id(), &mut hasher);
@@ -479,3 +1012,26 @@ impl Wallet {
         draft_name
     }
 }
+
+// the directory of a wallet's files under `dir`, which may be moved or deleted, so it
+// never resolves anywhere else. Names are only validated when a wallet is created or
+// renamed, wallets stored before that may have names that aren't a single path
+// component. Their files go to `legacy-<hex of the name>` instead, so the wallet can
+// still be loaded, renamed to a valid name or removed; files written under the old
+// name are left where they are.
+fn wallet_subdir(dir: &Path, wallet_name: &str) -> PathBuf {
+    let wallet_dir = dir.join(wallet_name);
+    if validate_wallet_name(wallet_name).is_ok() && wallet_dir.parent() == Some(dir) {
+        return wallet_dir;
+    }
+    let hex: String = wallet_name.bytes().map(|b| format!("{:02x}", b)).collect();
+    dir.join(format!("legacy-{}", hex))
+}
+
+// checks a draft file is the transaction described by its metadata before it is
+// signed, a file that can't be read is refused like one that doesn't match
+fn check_draft(draft: &NockchainTx) -> Result<(), String> {
+    let contents = inspect::read(Path::new(&draft.location))?;
+    inspect::check(&draft.metadata, &contents)
+        .map_err(|e| format!("draft does not match its details: {}", e))
+}
@@ -6,6 +6,7 @@ use nockapp::utils::make_tas;
 
 use nockvm::noun::{Noun, D, T};
 
+use crate::decode;
 use crate::manager::{NockchainPeek, NockchainStatus};
 
 pub fn status_driver(
@@ -31,11 +32,15 @@ pub fn status_driver(
                     tracing::error!("peek failed");
                     continue;
                 };
-                let Ok(noun) = clean_peek_noun(peek) else {
-                    tracing::error!("invalid peek noun");
+                let Some(slab) = peek else {
+                    tracing::error!("peek is empty");
                     continue;
                 };
-                let response = NockchainStatus::new(peek_command, noun);
+                if let Err(e) = decode::peek::<Noun>(&slab) {
+                    tracing::error!("invalid peek noun: {}", e);
+                    continue;
+                }
+                let response = NockchainStatus::new(peek_command, slab);
                 let Ok(_) = status_receiver_tx.send(response).await else {
                     tracing::error!("failed to send status");
                     continue;
@@ -55,20 +60,3 @@ fn make_slab(slab: &mut NounSlab, command: &str) -> NounSlab {
     slab.set_root(peek_noun);
     slab.clone()
 }
-
-fn clean_peek_noun(result: Option<NounSlab>) -> Result<Noun, String> {
-    let Some(result) = result else {
-        tracing::error!("peek is empty");
-        return Err("peek is empty".to_string());
-    };
-    let effect = unsafe { result.root() };
-    let unit_noun = effect
-        .as_cell()
-        .map_err(|_| "invalid noun".to_string())?
-        .tail();
-    let noun = unit_noun
-        .as_cell()
-        .map_err(|_| "invalid noun".to_string())?
-        .tail();
-    Ok(noun)
-}
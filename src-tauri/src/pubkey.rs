@@ -0,0 +1,293 @@
+// pubkey.rs
+//
+// Public keys and the locks built from them. A pubkey is written in base58 and
+// decodes to the 97 bytes of a point on the Cheetah curve. A lock says who can
+// spend a note: it is passed to the wallet kernel as `[m pk1 ... pkn]`, any `m`
+// of the listed keys can spend.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Length of a decoded pubkey in bytes.
+pub const PUBKEY_LEN: usize = 97;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Pubkey(String);
+
+impl Pubkey {
+    /// Validates a base58 pubkey, ignoring surrounding whitespace.
+    pub fn parse(pubkey: &str) -> Result<Self, String> {
+        let pubkey = pubkey.trim();
+        if pubkey.is_empty() {
+            return Err("pubkey is empty".to_string());
+        }
+        let bytes = bs58::decode(pubkey)
+            .into_vec()
+            .map_err(|e| format!("pubkey is not valid base58: {}", e))?;
+        if bytes.len() != PUBKEY_LEN {
+            return Err(format!(
+                "pubkey decodes to {} bytes, expected {}",
+                bytes.len(),
+                PUBKEY_LEN
+            ));
+        }
+        // re-encode so equal keys compare equal
+        Ok(Self(bs58::encode(bytes).into_string()))
+    }
+}
+
+impl FromStr for Pubkey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pubkey::parse(s)
+    }
+}
+
+impl fmt::Display for Pubkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for Pubkey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pubkey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pubkey = String::deserialize(deserializer)?;
+        Pubkey::parse(&pubkey).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The lock of a transaction output, spendable by `threshold` of `keys`.
+#[derive(Clone, Debug)]
+pub struct Lock {
+    threshold: u64,
+    keys: Vec<Pubkey>,
+}
+
+impl Lock {
+    /// A lock spendable by `pubkey` alone.
+    pub fn single(pubkey: Pubkey) -> Self {
+        Self {
+            threshold: 1,
+            keys: vec![pubkey],
+        }
+    }
+
+    /// An m-of-n lock, spendable by any `threshold` of `keys`.
+    pub fn multisig(threshold: u64, keys: Vec<Pubkey>) -> Result<Self, String> {
+        if keys.is_empty() {
+            return Err("lock has no pubkeys".to_string());
+        }
+        if threshold == 0 || threshold > keys.len() as u64 {
+            return Err(format!(
+                "lock threshold must be between 1 and {}",
+                keys.len()
+            ));
+        }
+        if let Some(i) = (1..keys.len()).find(|i| keys[..*i].contains(&keys[*i])) {
+            return Err(format!("pubkey {} is listed twice", keys[i]));
+        }
+        Ok(Self { threshold, keys })
+    }
+
+    /// Parses a bare pubkey as a 1-of-1 lock, or a lock written as `[m pk1 ... pkn]`.
+    pub fn parse(lock: &str) -> Result<Self, String> {
+        let lock = lock.trim();
+        let Some(inner) = lock.strip_prefix('[') else {
+            return Ok(Lock::single(Pubkey::parse(lock)?));
+        };
+        let Some(inner) = inner.strip_suffix(']') else {
+            return Err("lock is missing its closing ]".to_string());
+        };
+        let mut parts = inner.split_whitespace();
+        let threshold = parts
+            .next()
+            .ok_or("lock is empty".to_string())?
+            .parse::<u64>()
+            .map_err(|_| "lock must start with the number of signatures needed".to_string())?;
+        let keys = parts
+            .map(Pubkey::parse)
+            .collect::<Result<Vec<Pubkey>, String>>()?;
+        Lock::multisig(threshold, keys)
+    }
+
+    pub fn threshold(&self) -> u64 {
+        self.threshold
+    }
+
+    pub fn keys(&self) -> &[Pubkey] {
+        &self.keys
+    }
+}
+
+/// Parses the lock of an output to `recipient`. Fails if it is `own`, the lock of the
+/// sending wallet, unless sending to self is allowed.
+pub fn recipient_lock(
+    recipient: &str,
+    own: Option<&Lock>,
+    allow_self_send: bool,
+) -> Result<Lock, String> {
+    let lock =
+        Lock::parse(recipient).map_err(|e| format!("invalid recipient {:?}: {}", recipient, e))?;
+    if !allow_self_send && own == Some(&lock) {
+        return Err(format!(
+            "recipient {} is this wallet itself, allow sending to self to continue",
+            lock
+        ));
+    }
+    Ok(lock)
+}
+
+/// The keys of a lock are a set, two locks are equal whatever order their keys are listed in.
+impl PartialEq for Lock {
+    fn eq(&self, other: &Self) -> bool {
+        self.threshold == other.threshold
+            && self.keys.len() == other.keys.len()
+            && self.keys.iter().all(|key| other.keys.contains(key))
+    }
+}
+
+impl Eq for Lock {}
+
+impl FromStr for Lock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Lock::parse(s)
+    }
+}
+
+/// Writes the lock in the `[m pk1 ... pkn]` form the wallet kernel takes.
+impl fmt::Display for Lock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}", self.threshold)?;
+        for key in &self.keys {
+            write!(f, " {}", key)?;
+        }
+        f.write_str("]")
+    }
+}
+
+impl Serialize for Lock {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Lock {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let lock = String::deserialize(deserializer)?;
+        Lock::parse(&lock).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> String {
+        bs58::encode([byte; PUBKEY_LEN]).into_string()
+    }
+
+    fn pubkey(byte: u8) -> Pubkey {
+        Pubkey::parse(&key(byte)).unwrap()
+    }
+
+    #[test]
+    fn pubkeys_must_decode_to_a_point() {
+        assert_eq!(Pubkey::parse(&format!("  {}\n", key(1))), Ok(pubkey(1)));
+        for bytes in [0, 1, PUBKEY_LEN - 1, PUBKEY_LEN + 1] {
+            let short = bs58::encode(vec![7; bytes]).into_string();
+            assert!(Pubkey::parse(&short).is_err(), "{} bytes accepted", bytes);
+        }
+        // 0, O, I and l aren't in the base58 alphabet
+        for bad in [
+            "",
+            "  ",
+            "0OIl",
+            &format!("{}0", key(1)),
+            &key(1).replace('1', "l"),
+        ] {
+            assert!(Pubkey::parse(bad).is_err(), "{:?} accepted", bad);
+        }
+    }
+
+    #[test]
+    fn lock_thresholds_are_checked() {
+        let keys = vec![pubkey(1), pubkey(2), pubkey(3)];
+        assert!(Lock::multisig(0, keys.clone()).is_err());
+        assert!(Lock::multisig(4, keys.clone()).is_err());
+        assert!(Lock::multisig(1, Vec::new()).is_err());
+        for threshold in 1..=3 {
+            let lock = Lock::multisig(threshold, keys.clone()).unwrap();
+            assert_eq!(lock.threshold(), threshold);
+            assert_eq!(lock.keys(), &keys[..]);
+        }
+        assert!(Lock::parse(&format!("[0 {} {}]", key(1), key(2))).is_err());
+        assert!(Lock::parse(&format!("[3 {} {}]", key(1), key(2))).is_err());
+        assert!(Lock::parse(&format!("[two {} {}]", key(1), key(2))).is_err());
+        assert!(Lock::parse(&format!("[2 {} {}", key(1), key(2))).is_err());
+        assert!(Lock::parse("[]").is_err());
+        assert!(Lock::parse("[1]").is_err());
+    }
+
+    #[test]
+    fn lock_keys_are_distinct() {
+        let err = Lock::multisig(1, vec![pubkey(1), pubkey(2), pubkey(1)]).unwrap_err();
+        assert!(err.contains("listed twice"), "{}", err);
+        // a key repeated in a written lock is refused too
+        let lock = format!("[1 {} {}]", key(1), key(1));
+        assert!(Lock::parse(&lock).is_err());
+    }
+
+    #[test]
+    fn lock_equality_ignores_key_order() {
+        let lock = Lock::parse(&format!("[2 {} {} {}]", key(1), key(2), key(3))).unwrap();
+        let reordered = Lock::parse(&format!("[2 {} {} {}]", key(3), key(1), key(2))).unwrap();
+        assert_eq!(lock, reordered);
+        let other_threshold = Lock::multisig(1, reordered.keys().to_vec()).unwrap();
+        assert_ne!(lock, other_threshold);
+        let other_keys = Lock::parse(&format!("[2 {} {} {}]", key(1), key(2), key(4))).unwrap();
+        assert_ne!(lock, other_keys);
+        let fewer_keys = Lock::parse(&format!("[2 {} {}]", key(1), key(2))).unwrap();
+        assert_ne!(lock, fewer_keys);
+        assert_ne!(fewer_keys, lock);
+    }
+
+    #[test]
+    fn locks_round_trip() {
+        let single = Lock::parse(&key(1)).unwrap();
+        assert_eq!(single, Lock::single(pubkey(1)));
+        assert_eq!(single.to_string(), format!("[1 {}]", key(1)));
+        let lock = Lock::parse(&format!(" [2  {}\t{} ] ", key(1), key(2))).unwrap();
+        assert_eq!(Lock::parse(&lock.to_string()), Ok(lock.clone()));
+        let json = serde_json::to_string(&lock).unwrap();
+        assert_eq!(serde_json::from_str::<Lock>(&json).unwrap(), lock);
+    }
+
+    #[test]
+    fn sending_to_self_needs_to_be_allowed() {
+        let own = Lock::parse(&format!("[2 {} {}]", key(1), key(2))).unwrap();
+        let same = format!("[2 {} {}]", key(2), key(1));
+        let err = recipient_lock(&same, Some(&own), false).unwrap_err();
+        assert!(err.contains("this wallet itself"), "{}", err);
+        assert_eq!(recipient_lock(&same, Some(&own), true), Ok(own.clone()));
+        // one of the wallet's keys alone is another lock
+        assert!(recipient_lock(&key(1), Some(&own), false).is_ok());
+        assert!(recipient_lock(&same, None, false).is_ok());
+
+        let single = Lock::single(pubkey(3));
+        assert!(recipient_lock(&format!(" {} ", key(3)), Some(&single), false).is_err());
+        assert!(recipient_lock(&format!("[1 {}]", key(3)), Some(&single), false).is_err());
+        let err = recipient_lock("not a key", Some(&single), false).unwrap_err();
+        assert!(err.starts_with("invalid recipient"), "{}", err);
+    }
+}
@@ -0,0 +1,250 @@
+// seedphrase.rs
+//
+// Validation of BIP39 seedphrases before they are written to the vault: word
+// count, word list membership and checksum. Unknown words come back with the
+// closest words of the English list so typos can be fixed in place.
+
+use bip39::{Language, Mnemonic};
+use serde::Serialize;
+
+/// Number of words in a nockchain seedphrase.
+pub const WORD_COUNT: usize = 24;
+
+/// Largest edit distance for which a word list entry is offered as a suggestion.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+const MAX_SUGGESTIONS: usize = 3;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnknownWord {
+    /// 1-based position of the word in the seedphrase
+    pub position: usize,
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeedphraseCheck {
+    pub valid: bool,
+    pub word_count: usize,
+    pub unknown_words: Vec<UnknownWord>,
+    /// `None` until the count and all words are valid, the checksum can't be computed before that
+    pub checksum_valid: Option<bool>,
+}
+
+/// Lowercases and trims every word, dropping empty entries.
+pub fn normalize(words: &[String]) -> Vec<String> {
+    words
+        .iter()
+        .flat_map(|w| w.split_whitespace())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// Checks `words` (already normalized) against the English BIP39 list.
+pub fn check(words: &[String]) -> SeedphraseCheck {
+    let language = Language::English;
+    let unknown_words: Vec<UnknownWord> = words
+        .iter()
+        .enumerate()
+        .filter(|(_, word)| language.find_word(word).is_none())
+        .map(|(i, word)| UnknownWord {
+            position: i + 1,
+            suggestions: suggestions(word),
+        })
+        .collect();
+
+    let checksum_valid = if words.len() == WORD_COUNT && unknown_words.is_empty() {
+        Some(Mnemonic::parse_in_normalized(language, &words.join(" ")).is_ok())
+    } else {
+        None
+    };
+
+    SeedphraseCheck {
+        valid: checksum_valid == Some(true),
+        word_count: words.len(),
+        unknown_words,
+        checksum_valid,
+    }
+}
+
+/// Returns an error describing the first problem with `words`, if any.
+/// The words themselves are never included, only their positions and suggestions.
+pub fn validate(words: &[String]) -> Result<(), String> {
+    let check = check(words);
+    if check.word_count != WORD_COUNT {
+        return Err(format!(
+            "seedphrase must have {} words, got {}",
+            WORD_COUNT, check.word_count
+        ));
+    }
+    if let Some(unknown) = check.unknown_words.first() {
+        let mut msg = format!("word {} is not in the BIP39 word list", unknown.position);
+        if !unknown.suggestions.is_empty() {
+            msg.push_str(&format!(
+                ", did you mean {}?",
+                unknown.suggestions.join(" or ")
+            ));
+        }
+        return Err(msg);
+    }
+    if check.checksum_valid != Some(true) {
+        return Err("seedphrase checksum is invalid, check the word order".to_string());
+    }
+    Ok(())
+}
+
+// Word list entries closest to `word`. The English list is unique in its first
+// four letters, so a word with a matching 4-letter prefix is always offered first.
+fn suggestions(word: &str) -> Vec<String> {
+    let list = Language::English.word_list();
+    let mut candidates: Vec<(usize, &str)> = Vec::new();
+    if let Some(prefix) = word.get(..4) {
+        if let Some(prefixed) = Language::English.words_by_prefix(prefix).first() {
+            candidates.push((0, prefixed));
+        }
+    }
+    let mut by_distance: Vec<(usize, &str)> = list
+        .iter()
+        .map(|candidate| (levenshtein(word, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+        .collect();
+    by_distance.sort();
+    for (distance, candidate) in by_distance {
+        if !candidates.iter().any(|(_, c)| *c == candidate) {
+            candidates.push((distance, candidate));
+        }
+    }
+    candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, c)| c.to_string())
+        .collect()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the all-zero entropy phrase, its last word carries the checksum
+    fn phrase() -> Vec<String> {
+        let mut words = vec!["abandon".to_string(); WORD_COUNT - 1];
+        words.push("art".to_string());
+        words
+    }
+
+    #[test]
+    fn a_valid_phrase_passes() {
+        assert_eq!(validate(&phrase()), Ok(()));
+        let check = check(&phrase());
+        assert!(check.valid);
+        assert_eq!(check.word_count, WORD_COUNT);
+        assert!(check.unknown_words.is_empty());
+        assert_eq!(check.checksum_valid, Some(true));
+    }
+
+    #[test]
+    fn normalize_splits_trims_and_lowercases() {
+        let words = vec![
+            " Abandon  ABANDON ".to_string(),
+            String::new(),
+            "art\n".to_string(),
+        ];
+        assert_eq!(normalize(&words), vec!["abandon", "abandon", "art"]);
+    }
+
+    #[test]
+    fn word_count_is_checked_first() {
+        let mut words = phrase();
+        words.pop();
+        let err = validate(&words).unwrap_err();
+        assert_eq!(err, "seedphrase must have 24 words, got 23");
+        words.push("art".to_string());
+        words.push("art".to_string());
+        assert!(validate(&words).unwrap_err().contains("got 25"));
+        // an unknown word in a phrase of the wrong length doesn't get a checksum
+        words[0] = "abandn".to_string();
+        let check = check(&words);
+        assert_eq!(check.checksum_valid, None);
+        assert_eq!(check.unknown_words.len(), 1);
+        assert!(!check.valid);
+    }
+
+    #[test]
+    fn unknown_words_get_suggestions() {
+        let mut words = phrase();
+        words[4] = "abandn".to_string();
+        words[9] = "qqqqqqqq".to_string();
+        let check = check(&words);
+        assert!(!check.valid);
+        assert_eq!(check.checksum_valid, None);
+        let positions: Vec<usize> = check.unknown_words.iter().map(|u| u.position).collect();
+        assert_eq!(positions, vec![5, 10]);
+        assert_eq!(check.unknown_words[0].suggestions[0], "abandon");
+        assert!(check.unknown_words[1].suggestions.is_empty());
+
+        let err = validate(&words).unwrap_err();
+        assert!(err.starts_with("word 5 is not in the BIP39 word list, did you mean abandon"));
+        assert!(
+            !err.contains("abandn"),
+            "the word itself is left out: {}",
+            err
+        );
+
+        words[4] = "abandon".to_string();
+        assert_eq!(
+            validate(&words).unwrap_err(),
+            "word 10 is not in the BIP39 word list"
+        );
+    }
+
+    #[test]
+    fn bad_checksum_is_rejected() {
+        let words = vec!["abandon".to_string(); WORD_COUNT];
+        let check = check(&words);
+        assert!(check.unknown_words.is_empty());
+        assert_eq!(check.checksum_valid, Some(false));
+        assert!(!check.valid);
+        assert!(validate(&words)
+            .unwrap_err()
+            .contains("checksum is invalid"));
+    }
+
+    #[test]
+    fn suggestions_prefer_the_prefix_match() {
+        // "abstrakt" shares its first four letters with "abstract" only
+        assert_eq!(suggestions("abstrakt")[0], "abstract");
+        // one letter off from both "cat" and "car"
+        let close = suggestions("caq");
+        assert!(close.contains(&"cat".to_string()) && close.contains(&"car".to_string()));
+        assert!(close.len() <= MAX_SUGGESTIONS);
+        assert!(suggestions("xylophone").is_empty());
+    }
+
+    #[test]
+    fn levenshtein_counts_edits() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("abc", ""), 3);
+        assert_eq!(levenshtein("abandon", "abandon"), 0);
+        assert_eq!(levenshtein("abandn", "abandon"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("flaw", "lawn"), 2);
+        assert_eq!(levenshtein("ab", "ba"), 2);
+    }
+}
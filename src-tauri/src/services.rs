@@ -1,6 +1,6 @@
 use crate::manager::{
-    NockchainCommand, NockchainPeek, NockchainRequest, NockchainResponse, NockchainStatus,
-    WalletCommand,
+    redacted_command, NockchainCommand, NockchainPeek, NockchainRequest, NockchainResponse,
+    NockchainStatus, WalletCommand,
 };
 use crate::prover::Prover;
 use crate::wallet_app::WalletApp;
@@ -28,7 +28,7 @@ pub fn spawn_wallet_service(
         runtime.block_on(async move {
             info!("[Wallet Service] Started on a dedicated OS thread");
             while let Some(cmd) = wallet_rx.recv().await {
-                let command_name = format!("{:?}", cmd.command);
+                let command_name = redacted_command(&cmd.command);
                 info!("[Wallet Service] Received command: {}", command_name);
 
                 let future = AssertUnwindSafe(WalletApp::run(
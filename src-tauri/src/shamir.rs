@@ -0,0 +1,290 @@
+// shamir.rs
+//
+// Shamir secret sharing of seedphrases. The BIP39 entropy of a seedphrase is
+// split byte-wise over GF(256) into N shares, any M of which recover it. Each
+// share's data is written as a BIP39 mnemonic, so shares can be printed and
+// typed back like a seedphrase and carry their own word checksum. Shares of one
+// split share a random id; nothing derived from the seed is stored in a share, so
+// fewer than M shares reveal nothing about it.
+
+use bip39::{Language, Mnemonic};
+use rand::rngs::OsRng;
+use rand_core::TryRngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::secret::{Secret, SecretString};
+
+/// Length of the random split id in bytes.
+const SPLIT_ID_LEN: usize = 4;
+
+/// One share of a split seedphrase.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Share {
+    /// Random id of the split, the same for all of its shares.
+    /// Used to tell shares of different splits apart.
+    pub id: String,
+    pub threshold: u8,
+    /// 1-based share index, the x coordinate of the share
+    pub index: u8,
+    pub words: Secret<Vec<String>>,
+}
+
+/// Splits `seedphrase` into `shares` shares, `threshold` of which are needed to recover it.
+/// Share indices are the nonzero elements of GF(256), so at most 255 shares can be made.
+pub fn split(seedphrase: &SecretString, threshold: u8, shares: u8) -> Result<Vec<Share>, String> {
+    if threshold < 2 {
+        return Err("threshold must be at least 2".to_string());
+    }
+    if shares < threshold {
+        return Err("number of shares must be at least the threshold".to_string());
+    }
+
+    let secret = entropy(seedphrase)?;
+    let mut id = [0u8; SPLIT_ID_LEN];
+    OsRng
+        .try_fill_bytes(&mut id)
+        .map_err(|e| format!("Failed to generate split id: {}", e))?;
+    let id = id.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+
+    // one random polynomial per secret byte, with the byte as constant term
+    let mut coefficients = Secret::new(vec![0u8; secret.expose().len() * (threshold as usize - 1)]);
+    OsRng
+        .try_fill_bytes(coefficients.expose_mut())
+        .map_err(|e| format!("Failed to generate share coefficients: {}", e))?;
+
+    (1..=shares)
+        .map(|x| {
+            let mut data = Secret::new(
+                secret
+                    .expose()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, byte)| {
+                        let start = i * (threshold as usize - 1);
+                        let end = start + threshold as usize - 1;
+                        evaluate(*byte, &coefficients.expose()[start..end], x)
+                    })
+                    .collect::<Vec<u8>>(),
+            );
+            let words = to_words(data.expose())?;
+            data.expose_mut().zeroize();
+            Ok(Share {
+                id: id.clone(),
+                threshold,
+                index: x,
+                words,
+            })
+        })
+        .collect()
+}
+
+/// Recovers the seedphrase from at least `threshold` shares of the same split. A share
+/// with a mistyped word is caught by its checksum, but the recovered seed can't be
+/// checked against the original, so its wallet should be compared before it is used.
+pub fn recover(shares: &[Share]) -> Result<SecretString, String> {
+    let Some(first) = shares.first() else {
+        return Err("no shares given".to_string());
+    };
+    if shares
+        .iter()
+        .any(|s| s.id != first.id || s.threshold != first.threshold)
+    {
+        return Err("shares belong to different backups".to_string());
+    }
+    let mut indices = shares.iter().map(|s| s.index).collect::<Vec<u8>>();
+    indices.sort();
+    indices.dedup();
+    if indices.len() != shares.len() || indices.contains(&0) {
+        return Err("share indices must be distinct and nonzero".to_string());
+    }
+    if shares.len() < first.threshold as usize {
+        return Err(format!(
+            "{} shares are needed, got {}",
+            first.threshold,
+            shares.len()
+        ));
+    }
+
+    // any `threshold` shares determine the polynomials
+    let shares = &shares[..first.threshold as usize];
+    let points = shares
+        .iter()
+        .map(|share| Ok((share.index, from_words(&share.words, share.index)?)))
+        .collect::<Result<Vec<(u8, Secret<Vec<u8>>)>, String>>()?;
+    let len = points[0].1.expose().len();
+    if points.iter().any(|(_, data)| data.expose().len() != len) {
+        return Err("shares have different lengths".to_string());
+    }
+
+    let mut secret = Secret::new(vec![0u8; len]);
+    for (i, (x_i, y_i)) in points.iter().enumerate() {
+        // Lagrange basis polynomial of x_i, evaluated at 0
+        let mut basis = 1u8;
+        for (j, (x_j, _)) in points.iter().enumerate() {
+            if i != j {
+                basis = gf_mul(basis, gf_div(*x_j, *x_j ^ *x_i));
+            }
+        }
+        for (byte, y) in secret.expose_mut().iter_mut().zip(y_i.expose()) {
+            *byte ^= gf_mul(basis, *y);
+        }
+    }
+
+    let mnemonic = Mnemonic::from_entropy_in(Language::English, secret.expose())
+        .map_err(|e| format!("recovered seed is invalid: {}", e))?;
+    Ok(SecretString::new(
+        mnemonic.words().collect::<Vec<&str>>().join(" "),
+    ))
+}
+
+fn entropy(seedphrase: &SecretString) -> Result<Secret<Vec<u8>>, String> {
+    let mnemonic = Mnemonic::parse_in_normalized(Language::English, seedphrase.expose())
+        .map_err(|e| format!("seedphrase is not a valid BIP39 mnemonic: {}", e))?;
+    let (mut bytes, len) = mnemonic.to_entropy_array();
+    let entropy = Secret::new(bytes[..len].to_vec());
+    bytes.zeroize();
+    Ok(entropy)
+}
+
+fn to_words(data: &[u8]) -> Result<Secret<Vec<String>>, String> {
+    let mnemonic = Mnemonic::from_entropy_in(Language::English, data)
+        .map_err(|e| format!("Failed to encode share: {}", e))?;
+    Ok(Secret::new(mnemonic.words().map(str::to_string).collect()))
+}
+
+fn from_words(words: &Secret<Vec<String>>, index: u8) -> Result<Secret<Vec<u8>>, String> {
+    let phrase = SecretString::new(
+        words
+            .expose()
+            .iter()
+            .map(|w| w.trim().to_lowercase())
+            .collect::<Vec<String>>()
+            .join(" "),
+    );
+    entropy(&phrase).map_err(|e| format!("share {}: {}", index, e))
+}
+
+// Evaluates `secret + c1*x + c2*x^2 + ...` over GF(256) with Horner's rule.
+fn evaluate(secret: u8, coefficients: &[u8], x: u8) -> u8 {
+    let mut y = 0u8;
+    for c in coefficients.iter().rev() {
+        y = gf_mul(y, x) ^ c;
+    }
+    gf_mul(y, x) ^ secret
+}
+
+// Multiplication in GF(2^8) modulo the AES polynomial x^8 + x^4 + x^3 + x + 1,
+// without data-dependent branches.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+// a / b for b != 0, using b^-1 = b^254
+fn gf_div(a: u8, b: u8) -> u8 {
+    let mut inverse = 1u8;
+    let mut power = b;
+    let mut exponent = 254u8;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            inverse = gf_mul(inverse, power);
+        }
+        power = gf_mul(power, power);
+        exponent >>= 1;
+    }
+    gf_mul(a, inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seedphrase(fill: u8, len: usize) -> SecretString {
+        let mnemonic = Mnemonic::from_entropy_in(Language::English, &vec![fill; len]).unwrap();
+        SecretString::new(mnemonic.words().collect::<Vec<&str>>().join(" "))
+    }
+
+    // all subsets of `0..n` with `k` elements
+    fn subsets(n: usize, k: usize) -> Vec<Vec<usize>> {
+        (0u32..1 << n)
+            .filter(|mask| mask.count_ones() as usize == k)
+            .map(|mask| (0..n).filter(|i| mask & (1 << i) != 0).collect())
+            .collect()
+    }
+
+    #[test]
+    fn any_threshold_shares_recover_the_seed() {
+        for (threshold, count, len) in [(2, 3, 16), (3, 5, 32), (5, 5, 24)] {
+            let seed = seedphrase(0x5a, len);
+            let shares = split(&seed, threshold, count).unwrap();
+            for subset in subsets(count as usize, threshold as usize) {
+                let picked = subset
+                    .iter()
+                    .map(|i| shares[*i].clone())
+                    .collect::<Vec<Share>>();
+                let recovered = recover(&picked).unwrap();
+                assert_eq!(recovered.expose(), seed.expose(), "shares {:?}", subset);
+            }
+        }
+    }
+
+    #[test]
+    fn fewer_than_threshold_shares_fail() {
+        let shares = split(&seedphrase(1, 32), 3, 5).unwrap();
+        assert!(recover(&shares[..2]).is_err());
+        assert!(recover(&[]).is_err());
+    }
+
+    #[test]
+    fn shares_of_different_splits_are_rejected() {
+        let seed = seedphrase(2, 32);
+        let a = split(&seed, 2, 3).unwrap();
+        let b = split(&seed, 2, 3).unwrap();
+        assert_ne!(a[0].id, b[0].id);
+        let err = recover(&[a[0].clone(), b[1].clone()]).unwrap_err();
+        assert!(err.contains("different backups"), "{}", err);
+    }
+
+    #[test]
+    fn repeated_share_is_rejected() {
+        let shares = split(&seedphrase(3, 16), 2, 3).unwrap();
+        assert!(recover(&[shares[0].clone(), shares[0].clone()]).is_err());
+    }
+
+    #[test]
+    fn corrupted_share_word_fails_the_checksum() {
+        let shares = split(&seedphrase(4, 32), 2, 3).unwrap();
+        let mut corrupted = shares[0].clone();
+        let original = corrupted.words.expose()[0].clone();
+        // a replacement word almost never keeps the checksum valid, take one that breaks it
+        let replacement = Language::English
+            .word_list()
+            .iter()
+            .filter(|word| **word != original)
+            .find(|word| {
+                let mut words = shares[0].words.expose().clone();
+                words[0] = word.to_string();
+                Mnemonic::parse_in_normalized(Language::English, &words.join(" ")).is_err()
+            })
+            .unwrap();
+        corrupted.words.expose_mut()[0] = replacement.to_string();
+        let err = recover(&[corrupted, shares[1].clone()]).unwrap_err();
+        assert!(err.starts_with("share 1:"), "{}", err);
+    }
+
+    #[test]
+    fn split_checks_its_parameters() {
+        let seed = seedphrase(5, 16);
+        assert!(split(&seed, 1, 3).is_err());
+        assert!(split(&seed, 4, 3).is_err());
+        assert!(split(&SecretString::new("not a seedphrase".to_string()), 2, 3).is_err());
+    }
+}
@@ -0,0 +1,21 @@
+// timestamp.rs
+//
+// The current time as stored in vault, draft and history files: nanoseconds since
+// the Unix epoch as a decimal string, or whole seconds where only a coarse time is
+// needed.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Nanoseconds since the Unix epoch, as a decimal string.
+pub fn now_nanos() -> String {
+    since_epoch().as_nanos().to_string()
+}
+
+/// Seconds since the Unix epoch.
+pub fn now_secs() -> u64 {
+    since_epoch().as_secs()
+}
+
+fn since_epoch() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
+}
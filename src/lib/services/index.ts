@@ -14,6 +14,7 @@ console.log(useMock ? 'Using Mock API' : 'Using Real Tauri API');
 export const terms = useMock ? mock.terms : real.terms;
 export const updater = useMock ? mock.updater : real.updater;
 export const aeroe = useMock ? mock.aeroe : real.aeroe;
+export const session = useMock ? mock.session : real.session;
 export const vault = useMock ? mock.vault : real.vault;
 export const wallet = useMock ? mock.wallet : real.wallet;
 export const node = useMock ? mock.node : real.node; 
\ No newline at end of file
@@ -56,11 +56,19 @@ export const aeroe = {
         is_first_run: !mockState.vaultExists,
         vaultExists: mockState.vaultExists,
         vaultLoaded: false,
+        vaultBackups: 0,
+        unlockRetryIn: null,
         wallets: mockState.wallets,
+        watchOnlyWallets: [],
+        multisigWallets: [],
         activeWallet: mockState.activeWallet,
     }),
 };
 
+export const session = {
+    touch: () => mockInvoke<void>(undefined),
+};
+
 export const vault = {
     create: (password: string) => {
         console.log(`Mock vault created with password: ${password}`);
@@ -102,12 +110,17 @@ export const wallet = {
         const draftId = crypto.randomUUID();
         const newTx: NockchainTxMeta = {
             draftId,
-            transactions,
-            fee,
+            transactions: transactions.map(({ recipient, amount }) => ({ recipient, amount: String(amount) })),
+            fee: String(fee),
             createdAt: new Date().toISOString(),
             signedAt: null,
             broadcastedAt: null,
             status: 'draft',
+            inputs: [],
+            broadcastHeight: null,
+            confirmedHeight: null,
+            signedBy: [],
+            inputsMissingAt: null,
         };
         mockState.transactions[draftId] = newTx;
         console.log(`Mock transaction created for ${walletName}:`, newTx);
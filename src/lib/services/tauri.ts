@@ -17,7 +17,13 @@ export interface AeroeStatus {
   is_first_run: boolean;
   vaultExists: boolean;
   vaultLoaded: boolean;
+  // previous vault generations available for recovery
+  vaultBackups: number;
+  // seconds until another password attempt is accepted
+  unlockRetryIn: number | null;
   wallets: string[];
+  watchOnlyWallets: string[];
+  multisigWallets: string[];
   activeWallet: string | null;
   blockHeight?: number | null;
   masterNodeRunning?: boolean;
@@ -26,12 +32,37 @@ export interface AeroeStatus {
 
 export interface NockchainTxMeta {
   draftId: string;
-  transactions: { recipient: string, amount: number }[];
-  fee: number;
+  // amounts are in nicks, as decimal strings
+  transactions: { recipient: string, amount: string }[];
+  fee: string;
   createdAt: string;
   signedAt: string | null;
   broadcastedAt: string | null;
-  status: 'draft' | 'signed' | 'pending';
+  status: 'draft' | 'cosigning' | 'signed' | 'pending' | 'confirmed' | 'failed' | 'expired';
+  // notes spent by the transaction, as '[first last]'
+  inputs: string[];
+  broadcastHeight: number | null;
+  confirmedHeight: number | null;
+  // pubkeys that signed a multisig draft so far
+  signedBy: string[];
+  inputsMissingAt: number | null;
+}
+
+const NICKS_PER_NOCK = BigInt(65536);
+// 10^16 / 65536 is a whole number, so every fraction of a nock has an exact 16 digit decimal
+const FRACTION_SCALE = BigInt('152587890625');
+const FRACTION_DIGITS = 16;
+
+// sums nick amounts and formats them as nocks, e.g. ['98304'] -> '1.5'
+export function formatNock(nicks: string[]): string {
+    const total = nicks.reduce((acc, amount) => acc + BigInt(amount), BigInt(0));
+    const whole = total / NICKS_PER_NOCK;
+    const fraction = total % NICKS_PER_NOCK;
+    if (fraction === BigInt(0)) {
+        return whole.toString();
+    }
+    const decimals = (fraction * FRACTION_SCALE).toString().padStart(FRACTION_DIGITS, '0');
+    return `${whole}.${decimals.replace(/0+$/, '')}`;
 }
 
 async function handleInvoke<T>(command: string, args?: InvokeArgs): Promise<BackendResponse<T>> {
@@ -63,6 +94,10 @@ export const aeroe = {
     status: () => handleInvoke<AeroeStatus>('aeroe_status'),
 }
 
+export const session = {
+    touch: () => handleInvoke<void>('session_touch'),
+}
+
 export const vault = {
     create: (password: string) => handleInvoke<void>('vault_create', { password }),
     load: (password: string) => handleInvoke<void>('vault_load', { password }),